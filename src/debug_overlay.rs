@@ -0,0 +1,238 @@
+use crate::{ecs::registry::Registry, logger::Logger, renderer::Renderer, window::WindowInfo};
+use anyhow::Result;
+use sdl2::{pixels::Color, rect::Rect};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+pub const OVERLAY_POSITION: (i32, i32) = (8, 8);
+pub const OVERLAY_WIDTH: u32 = 420;
+pub const OVERLAY_PADDING: i32 = 6;
+pub const LINE_HEIGHT: i32 = 16;
+/// FPS and frame time are averaged over this many frames
+pub const FRAME_SAMPLES: usize = 60;
+pub const LOG_ENTRIES: usize = 5;
+pub const TEXT_COLOR: Color = Color::RGB(230, 230, 230);
+pub const BACKGROUND_COLOR: Color = Color::RGBA(0, 0, 0, 180);
+
+/// Frame and system statistics drawn on top of the game, toggled with F3
+#[derive(Default)]
+pub struct DebugOverlay {
+    is_visible: bool,
+    frame_times: VecDeque<f64>,
+    system_timings: BTreeMap<&'static str, Duration>,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.is_visible = !self.is_visible;
+    }
+
+    pub fn set_visible(&mut self, is_visible: bool) {
+        self.is_visible = is_visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.is_visible
+    }
+
+    /// `dt` is in seconds
+    pub fn record_frame(&mut self, dt: f64) {
+        if self.frame_times.len() == FRAME_SAMPLES {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(dt);
+    }
+
+    /// NOTE: Overrides the previous timing of the system
+    pub fn record_system_time(&mut self, system: &'static str, duration: Duration) {
+        self.system_timings.insert(system, duration);
+    }
+
+    /// Average frame time in seconds
+    pub fn get_frame_time(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+        self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+    }
+
+    pub fn get_fps(&self) -> f64 {
+        let frame_time = self.get_frame_time();
+        if frame_time > 0. {
+            1. / frame_time
+        } else {
+            0.
+        }
+    }
+
+    pub fn get_lines(&self, registry: &Registry, logger: &Logger) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "FPS: {:.1} ({:.2} ms)",
+                self.get_fps(),
+                self.get_frame_time() * 1000.
+            ),
+            format!(
+                "Entities: {}  Systems: {}",
+                registry.get_num_entities(),
+                registry.get_num_systems()
+            ),
+        ];
+
+        if let Ok(window) = registry.get_resource::<WindowInfo>() {
+            lines.push(format!(
+                "Window: {}x{} ({}x{} x{:.2}){}",
+                window.width,
+                window.height,
+                window.virtual_width,
+                window.virtual_height,
+                window.get_scale(),
+                if window.is_fullscreen {
+                    " fullscreen"
+                } else {
+                    ""
+                }
+            ));
+        }
+
+        for (system, count) in registry.get_system_entity_counts() {
+            let line = match self.system_timings.get(system) {
+                Some(duration) => format!(
+                    "  {}: {} entities, {:.3} ms",
+                    system,
+                    count,
+                    duration.as_secs_f64() * 1000.
+                ),
+                None => format!("  {}: {} entities", system, count),
+            };
+            lines.push(line);
+        }
+
+        let log_entries = logger.get_log_entires();
+        let recent = log_entries.len().saturating_sub(LOG_ENTRIES);
+        for entry in &log_entries[recent..] {
+            lines.push(format!("{:?}: {}", entry.level, entry.text));
+        }
+        lines
+    }
+
+    pub fn draw(
+        &self,
+        registry: &Registry,
+        logger: &Logger,
+        renderer: &mut dyn Renderer,
+    ) -> Result<()> {
+        if !self.is_visible {
+            return Ok(());
+        }
+
+        let lines = self.get_lines(registry, logger);
+        let height = lines.len() as i32 * LINE_HEIGHT + 2 * OVERLAY_PADDING;
+        renderer.fill_rect(
+            Rect::new(
+                OVERLAY_POSITION.0,
+                OVERLAY_POSITION.1,
+                OVERLAY_WIDTH,
+                height as u32,
+            ),
+            BACKGROUND_COLOR,
+        )?;
+
+        for (index, line) in lines.iter().enumerate() {
+            let position = (
+                OVERLAY_POSITION.0 + OVERLAY_PADDING,
+                OVERLAY_POSITION.1 + OVERLAY_PADDING + index as i32 * LINE_HEIGHT,
+            );
+            renderer.draw_text(line, position, TEXT_COLOR)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::{components::TransformComponent, systems::SystemMaskBuilder},
+        renderer::recording_renderer::{DrawCall, RecordingRenderer},
+    };
+
+    struct TestSystem;
+
+    fn create_registry() -> Result<Registry> {
+        let mut registry = Registry::default();
+        registry.register_component::<TransformComponent>()?;
+        let mask = SystemMaskBuilder::new(&registry)
+            .with::<TransformComponent>()?
+            .build();
+        registry.register_system::<TestSystem>(mask)?;
+        for _ in 0..2 {
+            let entity = registry.create_entity();
+            registry.add_component(entity, TransformComponent { position: (0., 0.) })?;
+        }
+        registry.add_resource(WindowInfo::new((1600, 1200), (800, 600)));
+        registry.update()?;
+        Ok(registry)
+    }
+
+    #[test]
+    fn overlay_lines_are_drawn_on_a_background() -> Result<()> {
+        let registry = create_registry()?;
+        let mut logger = Logger::default();
+        for index in 0..7 {
+            logger.log(&format!("message {}", index));
+        }
+        let mut overlay = DebugOverlay::new();
+        overlay.record_frame(0.01);
+        overlay.record_frame(0.03);
+        overlay.record_system_time("TestSystem", Duration::from_micros(1500));
+        overlay.toggle();
+
+        let mut renderer = RecordingRenderer::new();
+        overlay.draw(&registry, &logger, &mut renderer)?;
+
+        let mut lines = vec![
+            "FPS: 50.0 (20.00 ms)".to_string(),
+            "Entities: 2  Systems: 1".to_string(),
+            "Window: 1600x1200 (800x600 x2.00)".to_string(),
+            "  TestSystem: 2 entities, 1.500 ms".to_string(),
+        ];
+        // Only the most recent log entries
+        lines.extend((2..7).map(|index| format!("Info: message {}", index)));
+        let mut expected = vec![DrawCall::FillRect {
+            rect: Rect::new(8, 8, OVERLAY_WIDTH, 9 * 16 + 12),
+            color: BACKGROUND_COLOR,
+        }];
+        expected.extend(
+            lines
+                .into_iter()
+                .enumerate()
+                .map(|(index, text)| DrawCall::DrawText {
+                    text,
+                    position: (14, 14 + index as i32 * LINE_HEIGHT),
+                    color: TEXT_COLOR,
+                }),
+        );
+        assert_eq!(renderer.get_draw_calls(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn hidden_overlay_draws_nothing() -> Result<()> {
+        let registry = create_registry()?;
+        let mut overlay = DebugOverlay::new();
+        let mut renderer = RecordingRenderer::new();
+        overlay.draw(&registry, &Logger::default(), &mut renderer)?;
+        overlay.toggle();
+        overlay.toggle();
+        overlay.draw(&registry, &Logger::default(), &mut renderer)?;
+        assert!(renderer.get_draw_calls().is_empty());
+        Ok(())
+    }
+}
@@ -1,13 +1,1123 @@
+use super::{
+    ecs_errors::EcsErrors,
+    reflect::{field_does_not_exist, FieldInfo, FieldKind, Reflect, ReflectValue},
+    registry::Entity,
+};
+use anyhow::Result;
 use sdl2::pixels::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TransformComponent {
     pub position: (f64, f64),
 }
 
+/// Layers are drawn in declaration order, z_index sorts within a layer and entity id
+/// breaks the ties so the order is the same every frame
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub enum RenderLayer {
+    Background,
+    #[default]
+    World,
+    Effects,
+    Ui,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RenderComponent {
     pub width: u32,
     pub height: u32,
+    #[serde(with = "color_serde")]
     pub color: Color,
+    #[serde(default)]
+    pub layer: RenderLayer,
+    #[serde(default)]
+    pub z_index: i32,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct VelocityComponent(pub f64, pub f64);
+
+/// Axis aligned box at the TransformComponent position plus `offset`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BoxColliderComponent {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub offset: (f64, f64),
+}
+
+/// Use Registry::set_entity_name to rename, mutating it in place bypasses the name index
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NameComponent(pub String);
+
+/// Set on the children of an instantiated prefab, children are killed with their parent
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ParentComponent(pub Entity);
+
+/// Drawn at the TransformComponent position with the given size. `src_rect` is the part of
+/// the texture to draw as (x, y, width, height), None draws the whole texture.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SpriteComponent {
+    pub texture_id: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub src_rect: Option<(i32, i32, u32, u32)>,
+    #[serde(default)]
+    pub flip_horizontal: bool,
+    #[serde(default)]
+    pub flip_vertical: bool,
+    #[serde(default)]
+    pub layer: RenderLayer,
+    #[serde(default)]
+    pub z_index: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AnimationMode {
+    #[default]
+    Loop,
+    /// Stops on the last frame and fires an AnimationFinishedEvent
+    Once,
+    /// Plays forward then backward without repeating the first and the last frame
+    PingPong,
+}
+
+/// `frame_count` frames of the sprite sheet starting at `start_frame`, frames are numbered
+/// row by row
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AnimationClip {
+    pub start_frame: u32,
+    pub frame_count: u32,
+    /// In seconds
+    pub frame_duration: f64,
+    #[serde(default)]
+    pub mode: AnimationMode,
+}
+
+impl AnimationClip {
+    /// Index into the clip (not the sprite sheet) after `elapsed` seconds
+    pub fn get_frame_at(&self, elapsed: f64) -> u32 {
+        if self.frame_count <= 1 || self.frame_duration <= 0. {
+            return 0;
+        }
+        let step = (elapsed / self.frame_duration).max(0.) as u64;
+        let frame_count = self.frame_count as u64;
+        let frame = match self.mode {
+            AnimationMode::Loop => step % frame_count,
+            AnimationMode::Once => step.min(frame_count - 1),
+            AnimationMode::PingPong => {
+                let period = 2 * frame_count - 2;
+                let step = step % period;
+                if step < frame_count {
+                    step
+                } else {
+                    period - step
+                }
+            }
+        };
+        frame as u32
+    }
+
+    /// Length of one cycle in seconds
+    pub fn get_duration(&self) -> f64 {
+        let steps = match self.mode {
+            AnimationMode::PingPong if self.frame_count > 1 => 2 * self.frame_count - 2,
+            _ => self.frame_count,
+        };
+        steps as f64 * self.frame_duration
+    }
+}
+
+/// Advances the src_rect of the entity's SpriteComponent, see AnimationSystem.
+/// Use `play` to switch clips so the clip starts from its first frame.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AnimationComponent {
+    pub frame_width: u32,
+    pub frame_height: u32,
+    /// Frames per row of the sprite sheet
+    pub columns: u32,
+    pub clips: BTreeMap<String, AnimationClip>,
+    pub current_clip: String,
+    /// Seconds since the current clip started
+    #[serde(default)]
+    pub elapsed: f64,
+    #[serde(default)]
+    pub is_finished: bool,
+}
+
+impl AnimationComponent {
+    /// NOTE: Playing the clip that is already playing does not restart it, unless it is finished
+    pub fn play(&mut self, clip: &str) {
+        if self.current_clip == clip && !self.is_finished {
+            return;
+        }
+        self.current_clip = clip.to_string();
+        self.elapsed = 0.;
+        self.is_finished = false;
+    }
+
+    pub fn get_current_clip(&self) -> Option<&AnimationClip> {
+        self.clips.get(&self.current_clip)
+    }
+
+    /// Sprite sheet frame that is currently shown
+    pub fn get_current_frame(&self) -> Option<u32> {
+        let clip = self.get_current_clip()?;
+        Some(clip.start_frame + clip.get_frame_at(self.elapsed))
+    }
+
+    /// (x, y, width, height) of a sprite sheet frame, matches SpriteComponent::src_rect
+    pub fn get_frame_rect(&self, frame: u32) -> (i32, i32, u32, u32) {
+        let columns = self.columns.max(1);
+        (
+            ((frame % columns) * self.frame_width) as i32,
+            ((frame / columns) * self.frame_height) as i32,
+            self.frame_width,
+            self.frame_height,
+        )
+    }
+}
+
+pub const MIN_CAMERA_ZOOM: f64 = 0.01;
+
+/// A view into the world drawn into `viewport` (x, y, width, height) on the screen.
+/// `position` is the world position at the center of the view.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CameraComponent {
+    pub position: (f64, f64),
+    /// NOTE: Zooms below MIN_CAMERA_ZOOM (including 0) are treated as MIN_CAMERA_ZOOM
+    #[serde(default = "default_zoom")]
+    pub zoom: f64,
+    pub viewport: (i32, i32, u32, u32),
+    /// NOTE: RenderSystem draws through the first active camera (lowest entity id)
+    #[serde(default = "default_true")]
+    pub is_active: bool,
+    #[serde(default)]
+    pub target: Option<Entity>,
+    /// Looked up with Registry::find_by_name when `target` is None, level files use this
+    /// since they can not know entity ids
+    #[serde(default)]
+    pub target_name: Option<String>,
+    /// How fast the camera catches up with its target, 0 snaps to it every update
+    #[serde(default)]
+    pub smoothing: f64,
+    /// World area (x, y, width, height) the view is kept inside of, without bounds the
+    /// view is kept inside the map when the level has a MapSize
+    #[serde(default)]
+    pub bounds: Option<(f64, f64, f64, f64)>,
+}
+
+fn default_zoom() -> f64 {
+    1.
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl CameraComponent {
+    pub fn new(viewport: (i32, i32, u32, u32)) -> Self {
+        Self {
+            position: (viewport.2 as f64 / 2., viewport.3 as f64 / 2.),
+            zoom: 1.,
+            viewport,
+            is_active: true,
+            target: None,
+            target_name: None,
+            smoothing: 0.,
+            bounds: None,
+        }
+    }
+
+    /// The zoom the camera draws with, never 0 or negative
+    pub fn get_zoom(&self) -> f64 {
+        self.zoom.max(MIN_CAMERA_ZOOM)
+    }
+
+    /// Size of the visible world area
+    pub fn get_view_size(&self) -> (f64, f64) {
+        let zoom = self.get_zoom();
+        (self.viewport.2 as f64 / zoom, self.viewport.3 as f64 / zoom)
+    }
+
+    /// Visible world area as (x, y, width, height)
+    pub fn get_view_rect(&self) -> (f64, f64, f64, f64) {
+        let (width, height) = self.get_view_size();
+        (
+            self.position.0 - width / 2.,
+            self.position.1 - height / 2.,
+            width,
+            height,
+        )
+    }
+
+    pub fn world_to_screen(&self, world: (f64, f64)) -> (f64, f64) {
+        let (x, y, _, _) = self.get_view_rect();
+        let zoom = self.get_zoom();
+        (
+            self.viewport.0 as f64 + (world.0 - x) * zoom,
+            self.viewport.1 as f64 + (world.1 - y) * zoom,
+        )
+    }
+
+    pub fn screen_to_world(&self, screen: (f64, f64)) -> (f64, f64) {
+        let (x, y, _, _) = self.get_view_rect();
+        let zoom = self.get_zoom();
+        (
+            x + (screen.0 - self.viewport.0 as f64) / zoom,
+            y + (screen.1 - self.viewport.1 as f64) / zoom,
+        )
+    }
+
+    /// Moves the position so the view stays inside `bounds` (x, y, width, height), a view
+    /// bigger than the bounds is centered on them
+    pub fn clamp_to(&mut self, (x, y, width, height): (f64, f64, f64, f64)) {
+        let (view_width, view_height) = self.get_view_size();
+        self.position.0 = clamp_axis(self.position.0, x, width, view_width);
+        self.position.1 = clamp_axis(self.position.1, y, height, view_height);
+    }
+}
+
+fn clamp_axis(center: f64, start: f64, length: f64, view_length: f64) -> f64 {
+    if view_length >= length {
+        start + length / 2.
+    } else {
+        center.clamp(start + view_length / 2., start + length - view_length / 2.)
+    }
+}
+
+/// Points are offsets from the TransformComponent position
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    /// Centered on the position
+    Circle {
+        radius: f64,
+    },
+    /// From the position to the position plus `end`
+    Line {
+        end: (f64, f64),
+    },
+    Polygon {
+        points: Vec<(f64, f64)>,
+    },
+    /// The position is the top left corner
+    Rect {
+        width: f64,
+        height: f64,
+    },
+}
+
+/// A primitive shape drawn by RenderSystem, lines are always outlines
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ShapeComponent {
+    pub shape: Shape,
+    #[serde(with = "color_serde")]
+    pub color: Color,
+    #[serde(default)]
+    pub filled: bool,
+    /// Outline width in screen pixels, it does not scale with the camera zoom
+    #[serde(default = "default_stroke_width")]
+    pub stroke_width: u32,
+    #[serde(default)]
+    pub layer: RenderLayer,
+    #[serde(default)]
+    pub z_index: i32,
+}
+
+fn default_stroke_width() -> u32 {
+    1
+}
+
+impl ShapeComponent {
+    /// Area covered by the shape as (x, y, width, height), relative to the position
+    pub fn get_bounds(&self) -> (f64, f64, f64, f64) {
+        match &self.shape {
+            Shape::Circle { radius } => (-radius, -radius, radius * 2., radius * 2.),
+            Shape::Rect { width, height } => (0., 0., *width, *height),
+            Shape::Line { end } => get_points_bounds(&[(0., 0.), *end]),
+            Shape::Polygon { points } => get_points_bounds(points),
+        }
+    }
+}
+
+fn get_points_bounds(points: &[(f64, f64)]) -> (f64, f64, f64, f64) {
+    if points.is_empty() {
+        return (0., 0., 0., 0.);
+    }
+    let (mut min_x, mut min_y) = points[0];
+    let (mut max_x, mut max_y) = points[0];
+    for (x, y) in points {
+        min_x = min_x.min(*x);
+        min_y = min_y.min(*y);
+        max_x = max_x.max(*x);
+        max_y = max_y.max(*y);
+    }
+    (min_x, min_y, max_x - min_x, max_y - min_y)
+}
+
+/// Tile flag bits, see TilemapComponent::tile_flags
+pub const TILE_SOLID: u32 = 1;
+/// In tiles, RenderSystem culls tilemaps chunk by chunk
+pub const TILEMAP_CHUNK_SIZE: u32 = 16;
+/// Highest bits of a tile, set by the Tiled editor
+pub const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY_FLAG: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY_FLAG: u32 = 0x2000_0000;
+pub const ROTATED_HEXAGONAL_120_FLAG: u32 = 0x1000_0000;
+const FLIP_FLAGS_MASK: u32 = FLIPPED_HORIZONTALLY_FLAG
+    | FLIPPED_VERTICALLY_FLAG
+    | FLIPPED_DIAGONALLY_FLAG
+    | ROTATED_HEXAGONAL_120_FLAG;
+
+/// Global tile id without the flip flags
+pub fn get_tile_gid(tile: u32) -> u32 {
+    tile & !FLIP_FLAGS_MASK
+}
+
+/// Tiles are stored row by row as Tiled global tile ids, 0 is an empty tile and the
+/// highest bits are the flip flags
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TileLayer {
+    pub name: String,
+    pub tiles: Vec<u32>,
+    /// Offset from the TilemapComponent position
+    #[serde(default)]
+    pub offset: (f64, f64),
+    /// Hidden layers are not drawn but still count for queries, e.g. a collision layer
+    #[serde(default = "default_true")]
+    pub is_visible: bool,
+}
+
+/// Part of a texture cut into tiles, tile ids from `first_gid` to `first_gid + tile_count`
+/// belong to it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TilemapTileset {
+    pub texture_id: String,
+    pub first_gid: u32,
+    pub tile_count: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    #[serde(default)]
+    pub margin: u32,
+    #[serde(default)]
+    pub spacing: u32,
+}
+
+impl TilemapTileset {
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid < self.first_gid + self.tile_count
+    }
+
+    /// (x, y, width, height) of the tile in the texture
+    pub fn get_src_rect(&self, gid: u32) -> (i32, i32, u32, u32) {
+        let local_id = gid - self.first_gid;
+        let columns = self.columns.max(1);
+        let x = self.margin + (local_id % columns) * (self.tile_width + self.spacing);
+        let y = self.margin + (local_id / columns) * (self.tile_height + self.spacing);
+        (x as i32, y as i32, self.tile_width, self.tile_height)
+    }
+}
+
+/// A grid of tiles in one or more layers, RenderSystem only draws the chunks of
+/// TILEMAP_CHUNK_SIZE x TILEMAP_CHUNK_SIZE tiles that are in the camera view
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TilemapComponent {
+    /// In tiles
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Drawn in order, the first layer is at the bottom
+    pub layers: Vec<TileLayer>,
+    #[serde(default)]
+    pub tilesets: Vec<TilemapTileset>,
+    /// key => global tile id, value => TILE_ flag bits
+    #[serde(default)]
+    pub tile_flags: BTreeMap<u32, u32>,
+    #[serde(default)]
+    pub layer: RenderLayer,
+    #[serde(default)]
+    pub z_index: i32,
+}
+
+impl TilemapComponent {
+    /// Tile at (column, row) of a layer, including the flip flags
+    pub fn get_tile(&self, layer: usize, column: u32, row: u32) -> Option<u32> {
+        if column >= self.width || row >= self.height {
+            return None;
+        }
+        let index = (row * self.width + column) as usize;
+        self.layers.get(layer)?.tiles.get(index).copied()
+    }
+
+    pub fn get_tileset(&self, gid: u32) -> Option<&TilemapTileset> {
+        self.tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.contains(gid))
+    }
+
+    pub fn get_tile_flags(&self, tile: u32) -> u32 {
+        self.tile_flags
+            .get(&get_tile_gid(tile))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// (column, row) of the tile of `layer` under a world position, `origin` is the
+    /// TilemapComponent's TransformComponent position
+    pub fn world_to_tile(
+        &self,
+        origin: (f64, f64),
+        layer: usize,
+        world: (f64, f64),
+    ) -> Option<(u32, u32)> {
+        let offset = self.layers.get(layer)?.offset;
+        let x = (world.0 - origin.0 - offset.0) / self.tile_width as f64;
+        let y = (world.1 - origin.1 - offset.1) / self.tile_height as f64;
+        if x < 0. || y < 0. || x >= self.width as f64 || y >= self.height as f64 {
+            return None;
+        }
+        Some((x as u32, y as u32))
+    }
+
+    /// True if any layer has a tile with all of `flags` under the world position
+    pub fn has_flags_at(&self, origin: (f64, f64), world: (f64, f64), flags: u32) -> bool {
+        (0..self.layers.len()).any(|layer| {
+            self.world_to_tile(origin, layer, world)
+                .and_then(|(column, row)| self.get_tile(layer, column, row))
+                .is_some_and(|tile| self.get_tile_flags(tile) & flags == flags)
+        })
+    }
+
+    pub fn is_solid_at(&self, origin: (f64, f64), world: (f64, f64)) -> bool {
+        self.has_flags_at(origin, world, TILE_SOLID)
+    }
+
+    /// Number of chunks as (columns, rows)
+    pub fn get_chunk_count(&self) -> (u32, u32) {
+        (
+            self.width.div_ceil(TILEMAP_CHUNK_SIZE),
+            self.height.div_ceil(TILEMAP_CHUNK_SIZE),
+        )
+    }
+}
+
+/// A particle of a ParticleEmitterComponent pool, positions are world coordinates
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Particle {
+    pub position: (f64, f64),
+    pub velocity: (f64, f64),
+    /// Seconds since it was emitted
+    pub age: f64,
+    pub lifetime: f64,
+}
+
+impl Particle {
+    /// 0 when emitted, 1 at the end of its lifetime
+    pub fn get_life(&self) -> f64 {
+        if self.lifetime > 0. {
+            (self.age / self.lifetime).clamp(0., 1.)
+        } else {
+            1.
+        }
+    }
+}
+
+/// Emits particles from the TransformComponent position, see ParticleSystem.
+/// Particles are not entities, they live in `particles` and are drawn by RenderSystem as
+/// squares. Emission only depends on `seed`, so the same emitter always emits the same
+/// particles.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ParticleEmitterComponent {
+    /// Particles per second
+    #[serde(default)]
+    pub rate: f64,
+    /// Particles emitted at once on the first update
+    #[serde(default)]
+    pub burst: u32,
+    /// (min, max) in seconds
+    pub lifetime: (f64, f64),
+    /// In degrees, 0 points right and 90 points down
+    #[serde(default)]
+    pub direction: f64,
+    /// In degrees, particles leave within `direction` +- `spread` / 2
+    #[serde(default)]
+    pub spread: f64,
+    /// (min, max) in pixels per second
+    pub speed: (f64, f64),
+    /// Acceleration in pixels per second squared
+    #[serde(default)]
+    pub gravity: (f64, f64),
+    #[serde(with = "color_serde")]
+    pub start_color: Color,
+    #[serde(with = "color_serde")]
+    pub end_color: Color,
+    /// Width and height in pixels
+    pub start_size: f64,
+    pub end_size: f64,
+    /// NOTE: Particles over the limit are not emitted
+    #[serde(default = "default_max_particles")]
+    pub max_particles: usize,
+    #[serde(default = "default_true")]
+    pub is_emitting: bool,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub layer: RenderLayer,
+    #[serde(default)]
+    pub z_index: i32,
+    #[serde(default)]
+    pub particles: Vec<Particle>,
+    /// Particles to emit on the next update, on top of `rate`
+    #[serde(default)]
+    pub pending_burst: u32,
+    /// Fraction of a particle carried over to the next update
+    #[serde(default)]
+    pub emit_accumulator: f64,
+    #[serde(default)]
+    pub rng_state: u64,
+    #[serde(default)]
+    pub is_started: bool,
+}
+
+fn default_max_particles() -> usize {
+    256
+}
+
+impl ParticleEmitterComponent {
+    /// Emits `count` particles on the next update, for explosions and hit sparks
+    pub fn emit_burst(&mut self, count: u32) {
+        self.pending_burst += count;
+    }
+
+    /// True once a burst only emitter has no particles left
+    pub fn is_finished(&self) -> bool {
+        self.is_started
+            && (self.rate <= 0. || !self.is_emitting)
+            && self.pending_burst == 0
+            && self.particles.is_empty()
+    }
+
+    pub fn get_color_at(&self, life: f64) -> Color {
+        let lerp =
+            |start: u8, end: u8| (start as f64 + (end as f64 - start as f64) * life).round() as u8;
+        Color::RGBA(
+            lerp(self.start_color.r, self.end_color.r),
+            lerp(self.start_color.g, self.end_color.g),
+            lerp(self.start_color.b, self.end_color.b),
+            lerp(self.start_color.a, self.end_color.a),
+        )
+    }
+
+    pub fn get_size_at(&self, life: f64) -> f64 {
+        self.start_size + (self.end_size - self.start_size) * life
+    }
+
+    /// World area covered by the particles as (x, y, width, height)
+    pub fn get_bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        if self.particles.is_empty() {
+            return None;
+        }
+        let positions: Vec<(f64, f64)> = self
+            .particles
+            .iter()
+            .map(|particle| particle.position)
+            .collect();
+        let (x, y, width, height) = get_points_bounds(&positions);
+        let padding = self.start_size.max(self.end_size) / 2.;
+        Some((
+            x - padding,
+            y - padding,
+            width + 2. * padding,
+            height + 2. * padding,
+        ))
+    }
+}
+
+impl Reflect for TransformComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("position.0", FieldKind::Float),
+        FieldInfo::new("position.1", FieldKind::Float),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "position.0" => Some(ReflectValue::Float(self.position.0)),
+            "position.1" => Some(ReflectValue::Float(self.position.1)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "position.0" => self.position.0 = value.as_float(path)?,
+            "position.1" => self.position.1 = value.as_float(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for RenderComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("width", FieldKind::Int),
+        FieldInfo::new("height", FieldKind::Int),
+        FieldInfo::new("color.r", FieldKind::Int),
+        FieldInfo::new("color.g", FieldKind::Int),
+        FieldInfo::new("color.b", FieldKind::Int),
+        FieldInfo::new("color.a", FieldKind::Int),
+        FieldInfo::new("layer", FieldKind::String),
+        FieldInfo::new("z_index", FieldKind::Int),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        let value = match path {
+            "width" => self.width as i64,
+            "height" => self.height as i64,
+            "color.r" => self.color.r as i64,
+            "color.g" => self.color.g as i64,
+            "color.b" => self.color.b as i64,
+            "color.a" => self.color.a as i64,
+            "layer" => return Some(get_layer_value(self.layer)),
+            "z_index" => self.z_index as i64,
+            _ => return None,
+        };
+        Some(ReflectValue::Int(value))
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "width" => self.width = value.as_int_of(path)?,
+            "height" => self.height = value.as_int_of(path)?,
+            "color.r" => self.color.r = value.as_int_of(path)?,
+            "color.g" => self.color.g = value.as_int_of(path)?,
+            "color.b" => self.color.b = value.as_int_of(path)?,
+            "color.a" => self.color.a = value.as_int_of(path)?,
+            "layer" => self.layer = parse_layer(path, value)?,
+            "z_index" => self.z_index = value.as_int_of(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for VelocityComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("0", FieldKind::Float),
+        FieldInfo::new("1", FieldKind::Float),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "0" => Some(ReflectValue::Float(self.0)),
+            "1" => Some(ReflectValue::Float(self.1)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "0" => self.0 = value.as_float(path)?,
+            "1" => self.1 = value.as_float(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for BoxColliderComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("width", FieldKind::Int),
+        FieldInfo::new("height", FieldKind::Int),
+        FieldInfo::new("offset.0", FieldKind::Float),
+        FieldInfo::new("offset.1", FieldKind::Float),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "width" => Some(ReflectValue::Int(self.width as i64)),
+            "height" => Some(ReflectValue::Int(self.height as i64)),
+            "offset.0" => Some(ReflectValue::Float(self.offset.0)),
+            "offset.1" => Some(ReflectValue::Float(self.offset.1)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "width" => self.width = value.as_int_of(path)?,
+            "height" => self.height = value.as_int_of(path)?,
+            "offset.0" => self.offset.0 = value.as_float(path)?,
+            "offset.1" => self.offset.1 = value.as_float(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for SpriteComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("texture_id", FieldKind::String),
+        FieldInfo::new("width", FieldKind::Int),
+        FieldInfo::new("height", FieldKind::Int),
+        FieldInfo::new("flip_horizontal", FieldKind::Bool),
+        FieldInfo::new("flip_vertical", FieldKind::Bool),
+        FieldInfo::new("layer", FieldKind::String),
+        FieldInfo::new("z_index", FieldKind::Int),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "texture_id" => Some(ReflectValue::String(self.texture_id.clone())),
+            "width" => Some(ReflectValue::Int(self.width as i64)),
+            "height" => Some(ReflectValue::Int(self.height as i64)),
+            "flip_horizontal" => Some(ReflectValue::Bool(self.flip_horizontal)),
+            "flip_vertical" => Some(ReflectValue::Bool(self.flip_vertical)),
+            "layer" => Some(get_layer_value(self.layer)),
+            "z_index" => Some(ReflectValue::Int(self.z_index as i64)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "texture_id" => self.texture_id = value.as_string(path)?,
+            "width" => self.width = value.as_int_of(path)?,
+            "height" => self.height = value.as_int_of(path)?,
+            "flip_horizontal" => self.flip_horizontal = value.as_bool(path)?,
+            "flip_vertical" => self.flip_vertical = value.as_bool(path)?,
+            "layer" => self.layer = parse_layer(path, value)?,
+            "z_index" => self.z_index = value.as_int_of(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for AnimationComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("current_clip", FieldKind::String),
+        FieldInfo::new("elapsed", FieldKind::Float),
+        FieldInfo::new("is_finished", FieldKind::Bool),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "current_clip" => Some(ReflectValue::String(self.current_clip.clone())),
+            "elapsed" => Some(ReflectValue::Float(self.elapsed)),
+            "is_finished" => Some(ReflectValue::Bool(self.is_finished)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "current_clip" => self.current_clip = value.as_string(path)?,
+            "elapsed" => self.elapsed = value.as_float(path)?,
+            "is_finished" => self.is_finished = value.as_bool(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for CameraComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("position.0", FieldKind::Float),
+        FieldInfo::new("position.1", FieldKind::Float),
+        FieldInfo::new("zoom", FieldKind::Float),
+        FieldInfo::new("is_active", FieldKind::Bool),
+        FieldInfo::new("smoothing", FieldKind::Float),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "position.0" => Some(ReflectValue::Float(self.position.0)),
+            "position.1" => Some(ReflectValue::Float(self.position.1)),
+            "zoom" => Some(ReflectValue::Float(self.zoom)),
+            "is_active" => Some(ReflectValue::Bool(self.is_active)),
+            "smoothing" => Some(ReflectValue::Float(self.smoothing)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "position.0" => self.position.0 = value.as_float(path)?,
+            "position.1" => self.position.1 = value.as_float(path)?,
+            "zoom" => self.zoom = value.as_float(path)?,
+            "is_active" => self.is_active = value.as_bool(path)?,
+            "smoothing" => self.smoothing = value.as_float(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for ShapeComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("color.r", FieldKind::Int),
+        FieldInfo::new("color.g", FieldKind::Int),
+        FieldInfo::new("color.b", FieldKind::Int),
+        FieldInfo::new("color.a", FieldKind::Int),
+        FieldInfo::new("filled", FieldKind::Bool),
+        FieldInfo::new("stroke_width", FieldKind::Int),
+        FieldInfo::new("layer", FieldKind::String),
+        FieldInfo::new("z_index", FieldKind::Int),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        let value = match path {
+            "color.r" => self.color.r as i64,
+            "color.g" => self.color.g as i64,
+            "color.b" => self.color.b as i64,
+            "color.a" => self.color.a as i64,
+            "filled" => return Some(ReflectValue::Bool(self.filled)),
+            "stroke_width" => self.stroke_width as i64,
+            "layer" => return Some(get_layer_value(self.layer)),
+            "z_index" => self.z_index as i64,
+            _ => return None,
+        };
+        Some(ReflectValue::Int(value))
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "color.r" => self.color.r = value.as_int_of(path)?,
+            "color.g" => self.color.g = value.as_int_of(path)?,
+            "color.b" => self.color.b = value.as_int_of(path)?,
+            "color.a" => self.color.a = value.as_int_of(path)?,
+            "filled" => self.filled = value.as_bool(path)?,
+            "stroke_width" => self.stroke_width = value.as_int_of(path)?,
+            "layer" => self.layer = parse_layer(path, value)?,
+            "z_index" => self.z_index = value.as_int_of(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for TilemapComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("layer", FieldKind::String),
+        FieldInfo::new("z_index", FieldKind::Int),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "layer" => Some(get_layer_value(self.layer)),
+            "z_index" => Some(ReflectValue::Int(self.z_index as i64)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "layer" => self.layer = parse_layer(path, value)?,
+            "z_index" => self.z_index = value.as_int_of(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+impl Reflect for ParticleEmitterComponent {
+    const FIELDS: &'static [FieldInfo] = &[
+        FieldInfo::new("rate", FieldKind::Float),
+        FieldInfo::new("burst", FieldKind::Int),
+        FieldInfo::new("direction", FieldKind::Float),
+        FieldInfo::new("spread", FieldKind::Float),
+        FieldInfo::new("gravity.0", FieldKind::Float),
+        FieldInfo::new("gravity.1", FieldKind::Float),
+        FieldInfo::new("start_size", FieldKind::Float),
+        FieldInfo::new("end_size", FieldKind::Float),
+        FieldInfo::new("max_particles", FieldKind::Int),
+        FieldInfo::new("is_emitting", FieldKind::Bool),
+        FieldInfo::new("layer", FieldKind::String),
+        FieldInfo::new("z_index", FieldKind::Int),
+    ];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue> {
+        match path {
+            "rate" => Some(ReflectValue::Float(self.rate)),
+            "burst" => Some(ReflectValue::Int(self.burst as i64)),
+            "direction" => Some(ReflectValue::Float(self.direction)),
+            "spread" => Some(ReflectValue::Float(self.spread)),
+            "gravity.0" => Some(ReflectValue::Float(self.gravity.0)),
+            "gravity.1" => Some(ReflectValue::Float(self.gravity.1)),
+            "start_size" => Some(ReflectValue::Float(self.start_size)),
+            "end_size" => Some(ReflectValue::Float(self.end_size)),
+            "max_particles" => Some(ReflectValue::Int(self.max_particles as i64)),
+            "is_emitting" => Some(ReflectValue::Bool(self.is_emitting)),
+            "layer" => Some(get_layer_value(self.layer)),
+            "z_index" => Some(ReflectValue::Int(self.z_index as i64)),
+            _ => None,
+        }
+    }
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()> {
+        match path {
+            "rate" => self.rate = value.as_float(path)?,
+            "burst" => self.burst = value.as_int_of(path)?,
+            "direction" => self.direction = value.as_float(path)?,
+            "spread" => self.spread = value.as_float(path)?,
+            "gravity.0" => self.gravity.0 = value.as_float(path)?,
+            "gravity.1" => self.gravity.1 = value.as_float(path)?,
+            "start_size" => self.start_size = value.as_float(path)?,
+            "end_size" => self.end_size = value.as_float(path)?,
+            "max_particles" => self.max_particles = value.as_int_of(path)?,
+            "is_emitting" => self.is_emitting = value.as_bool(path)?,
+            "layer" => self.layer = parse_layer(path, value)?,
+            "z_index" => self.z_index = value.as_int_of(path)?,
+            _ => return Err(field_does_not_exist::<Self>(path)),
+        }
+        Ok(())
+    }
+}
+
+/// Layers are reflected by their variant name, e.g. `Ui`
+fn get_layer_value(layer: RenderLayer) -> ReflectValue {
+    ReflectValue::String(format!("{:?}", layer))
+}
+
+fn parse_layer(path: &str, value: ReflectValue) -> Result<RenderLayer> {
+    let name = value.as_string(path)?;
+    serde_json::from_value(serde_json::Value::String(name.clone())).map_err(|_| {
+        EcsErrors::InvalidFieldValue {
+            field: path.to_string(),
+            value: name,
+        }
+        .into()
+    })
+}
+
+/// sdl2's Color has no serde support, it is stored as (r, g, b, a)
+pub mod color_serde {
+    use sdl2::pixels::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        (color.r, color.g, color.b, color.a).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let (r, g, b, a) = <(u8, u8, u8, u8)>::deserialize(deserializer)?;
+        Ok(Color::RGBA(r, g, b, a))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_tilemap() -> TilemapComponent {
+        // 3x2 tiles of 10x10, tile 2 is solid and only on the hidden collision layer
+        TilemapComponent {
+            width: 3,
+            height: 2,
+            tile_width: 10,
+            tile_height: 10,
+            layers: vec![
+                TileLayer {
+                    name: "ground".to_string(),
+                    tiles: vec![1, 1 | FLIPPED_HORIZONTALLY_FLAG, 0, 1, 1, 1],
+                    offset: (0., 0.),
+                    is_visible: true,
+                },
+                TileLayer {
+                    name: "collision".to_string(),
+                    tiles: vec![0, 0, 2 | FLIPPED_VERTICALLY_FLAG, 0, 0, 0],
+                    offset: (0., 0.),
+                    is_visible: false,
+                },
+            ],
+            tilesets: vec![],
+            tile_flags: BTreeMap::from([(2, TILE_SOLID)]),
+            layer: RenderLayer::Background,
+            z_index: 0,
+        }
+    }
+
+    #[test]
+    fn tile_gid_strips_flip_flags() {
+        assert_eq!(get_tile_gid(7), 7);
+        assert_eq!(
+            get_tile_gid(7 | FLIPPED_HORIZONTALLY_FLAG | FLIPPED_DIAGONALLY_FLAG),
+            7
+        );
+    }
+
+    #[test]
+    fn tiles_are_looked_up_by_column_and_row() {
+        let tilemap = create_tilemap();
+        assert_eq!(
+            tilemap.get_tile(0, 1, 0),
+            Some(1 | FLIPPED_HORIZONTALLY_FLAG)
+        );
+        assert_eq!(tilemap.get_tile(0, 2, 1), Some(1));
+        assert_eq!(tilemap.get_tile(0, 3, 0), None);
+        assert_eq!(tilemap.get_tile(2, 0, 0), None);
+        assert_eq!(tilemap.get_chunk_count(), (1, 1));
+    }
+
+    #[test]
+    fn solid_tiles_are_found_on_hidden_layers() {
+        let tilemap = create_tilemap();
+        let origin = (100., 50.);
+        assert_eq!(tilemap.world_to_tile(origin, 0, (125., 55.)), Some((2, 0)));
+        assert_eq!(tilemap.world_to_tile(origin, 0, (99., 55.)), None);
+        assert!(tilemap.is_solid_at(origin, (125., 55.)));
+        assert!(!tilemap.is_solid_at(origin, (115., 55.)));
+        assert!(!tilemap.is_solid_at(origin, (125., 65.)));
+    }
+
+    fn get_frames(frame_count: u32, frame_duration: f64, mode: AnimationMode) -> Vec<u32> {
+        let clip = AnimationClip {
+            start_frame: 4,
+            frame_count,
+            frame_duration,
+            mode,
+        };
+        // Sampled in the middle of every frame
+        (0..8)
+            .map(|step| clip.get_frame_at((step as f64 + 0.5) * 0.1))
+            .collect()
+    }
+
+    #[test]
+    fn clips_step_through_their_frames_by_mode() {
+        assert_eq!(
+            get_frames(3, 0.1, AnimationMode::Loop),
+            [0, 1, 2, 0, 1, 2, 0, 1]
+        );
+        assert_eq!(
+            get_frames(3, 0.1, AnimationMode::Once),
+            [0, 1, 2, 2, 2, 2, 2, 2]
+        );
+        assert_eq!(
+            get_frames(4, 0.1, AnimationMode::PingPong),
+            [0, 1, 2, 3, 2, 1, 0, 1]
+        );
+    }
+
+    #[test]
+    fn clips_without_frames_or_duration_stay_on_the_first_frame() {
+        for mode in [
+            AnimationMode::Loop,
+            AnimationMode::Once,
+            AnimationMode::PingPong,
+        ] {
+            assert_eq!(get_frames(0, 0.1, mode), [0; 8]);
+            assert_eq!(get_frames(1, 0.1, mode), [0; 8]);
+            assert_eq!(get_frames(3, 0., mode), [0; 8]);
+            assert_eq!(get_frames(3, -0.1, mode), [0; 8]);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use super::reflect::FieldKind;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,12 +7,54 @@ pub enum EcsErrors {
         "Max component count has been reached. Try removing a component or increase MAXCOMPONENTS"
     )]
     MaxComponentReached,
-    #[error("Component is not registered")]
-    ComponentDoesNotExist,
+    #[error("Component {0} is not registered")]
+    ComponentDoesNotExist(String),
+    #[error("Component name {0} is already taken")]
+    ComponentNameTaken(String),
+    #[error("Value added as component {0} is of another type")]
+    ComponentTypeMismatch(String),
+    #[error("Entity {entity} does not have component {component}")]
+    EntityDoesNotHaveComponent { entity: String, component: String },
     #[error("Component mask does not exist")]
     ComponentMaskDoesNotExist,
+    #[error("Entity {0} does not exist")]
+    EntityDoesNotExist(usize),
+    #[error("Entity {0} is killed")]
+    EntityIsKilled(usize),
     #[error("Entity component mask does not exist")]
     EntityComponentMaskDoesNotExist,
     #[error("System is not registered")]
     SystemDoesNotExist,
+    #[error("Resource does not exist")]
+    ResourceDoesNotExist,
+    #[error("Component {0} has no serializer. Register one with register_component_serializer")]
+    ComponentNotSerializable(String),
+    #[error("Snapshot version {0} is not supported")]
+    SnapshotVersionNotSupported(u16),
+    #[error("Snapshot was made with a different component schema (expected hash {expected:#018x}, found {found:#018x})")]
+    SnapshotSchemaMismatch { expected: u64, found: u64 },
+    #[error("Snapshot is corrupted: {0}")]
+    SnapshotCorrupted(String),
+    #[error("Frame {0} is not in the rollback buffer")]
+    RollbackFrameNotAvailable(u64),
+    #[error("Component {0} has no cloner. Register one with register_component_cloner")]
+    ComponentNotCloneable(String),
+    #[error("World {0} does not exist")]
+    WorldDoesNotExist(String),
+    #[error("World name {0} is already taken")]
+    WorldNameTaken(String),
+    #[error("World {0} is active and can not be removed")]
+    WorldIsActive(String),
+    #[error("Can not move entities from world {0} into itself")]
+    CannotMoveToSameWorld(String),
+    #[error("Prefab {0} is not registered")]
+    PrefabDoesNotExist(String),
+    #[error("Component {0} has no reflection. Register it with register_component_reflection")]
+    ComponentNotReflectable(String),
+    #[error("Component {component} has no field {field}")]
+    FieldDoesNotExist { component: String, field: String },
+    #[error("Field {field} expects a {expected:?} value")]
+    FieldTypeMismatch { field: String, expected: FieldKind },
+    #[error("{value} is not a valid value for field {field}")]
+    InvalidFieldValue { field: String, value: String },
 }
@@ -2,3 +2,8 @@ pub mod registry;
 pub mod systems;
 pub mod components;
 pub mod ecs_errors;
+pub mod snapshot;
+pub mod rollback;
+pub mod reflect;
+pub mod prefab;
+pub mod world;
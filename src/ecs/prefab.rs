@@ -0,0 +1,190 @@
+use super::{
+    ecs_errors::EcsErrors,
+    registry::{Component, Registry},
+};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    any::Any,
+    any::TypeId,
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// Creates a fresh copy of a component every time a prefab is instantiated
+pub type ComponentFactory = Rc<dyn Fn() -> Result<Component>>;
+
+pub fn clone_factory<T: Any + Clone>(data: T) -> ComponentFactory {
+    Rc::new(move || Ok(Rc::new(RefCell::new(data.clone())) as Component))
+}
+
+/// A named template of component values, instantiated with `Registry::instantiate_prefab`
+#[derive(Clone)]
+pub struct Prefab {
+    pub name: String,
+    components: Vec<(TypeId, ComponentFactory)>,
+    children: Vec<Prefab>,
+}
+
+impl Prefab {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            components: vec![],
+            children: vec![],
+        }
+    }
+
+    /// NOTE: If you add the same component again it will override
+    pub fn with<T: Any + Clone>(self, data: T) -> Self {
+        self.with_factory(TypeId::of::<T>(), clone_factory(data))
+    }
+
+    pub fn with_factory(mut self, type_id: TypeId, factory: ComponentFactory) -> Self {
+        self.components
+            .retain(|(component_id, _)| *component_id != type_id);
+        self.components.push((type_id, factory));
+        self
+    }
+
+    /// Children get a ParentComponent pointing at the instantiated root and their
+    /// TransformComponent is treated as an offset from the root's position
+    pub fn with_child(mut self, child: Prefab) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn get_components(&self) -> &[(TypeId, ComponentFactory)] {
+        &self.components
+    }
+
+    pub fn get_children(&self) -> &[Prefab] {
+        &self.children
+    }
+}
+
+/// Per instance component values that replace (or extend) the prefab's root components
+#[derive(Clone, Default)]
+pub struct PrefabOverrides {
+    components: Vec<(TypeId, ComponentFactory)>,
+}
+
+impl PrefabOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<T: Any + Clone>(self, data: T) -> Self {
+        self.with_factory(TypeId::of::<T>(), clone_factory(data))
+    }
+
+    pub fn with_factory(mut self, type_id: TypeId, factory: ComponentFactory) -> Self {
+        self.components
+            .retain(|(component_id, _)| *component_id != type_id);
+        self.components.push((type_id, factory));
+        self
+    }
+
+    pub fn get(&self, type_id: TypeId) -> Option<&ComponentFactory> {
+        self.components
+            .iter()
+            .find(|(component_id, _)| *component_id == type_id)
+            .map(|(_, factory)| factory)
+    }
+
+    pub fn get_components(&self) -> &[(TypeId, ComponentFactory)] {
+        &self.components
+    }
+}
+
+/// Prefab file layout, components are keyed by their registered name:
+/// `{ "name": "enemy", "components": { "TransformComponent": { "position": [0, 0] } }, "children": [] }`
+#[derive(Deserialize)]
+struct PrefabFile {
+    name: String,
+    #[serde(default)]
+    components: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    children: Vec<PrefabFile>,
+}
+
+impl PrefabFile {
+    fn into_prefab(self, registry: &Registry) -> Result<Prefab> {
+        let mut prefab = Prefab::new(&self.name);
+        for (component_name, value) in self.components {
+            let (type_id, factory) = component_factory_from_json(registry, &component_name, value)
+                .with_context(|| format!("prefab {}", self.name))?;
+            prefab = prefab.with_factory(type_id, factory);
+        }
+        for child in self.children {
+            prefab = prefab.with_child(child.into_prefab(registry)?);
+        }
+        Ok(prefab)
+    }
+}
+
+/// The value is deserialized once up front so mistakes are reported when the file is
+/// loaded rather than when it is instantiated
+pub fn component_factory_from_json(
+    registry: &Registry,
+    component_name: &str,
+    value: serde_json::Value,
+) -> Result<(TypeId, ComponentFactory)> {
+    let type_id = registry
+        .get_component_type_id(component_name)
+        .ok_or_else(|| EcsErrors::ComponentDoesNotExist(component_name.to_string()))?;
+    let from_json = registry
+        .get_component_serializer(type_id)
+        .ok_or_else(|| EcsErrors::ComponentNotSerializable(component_name.to_string()))?
+        .from_json;
+
+    from_json(value.clone())
+        .with_context(|| format!("invalid value for component {}", component_name))?;
+
+    let component_name = component_name.to_string();
+    let factory: ComponentFactory = Rc::new(move || {
+        from_json(value.clone())
+            .with_context(|| format!("invalid value for component {}", component_name))
+    });
+    Ok((type_id, factory))
+}
+
+pub fn load_prefab_from_json(json: &str, registry: &Registry) -> Result<Prefab> {
+    let prefab_file: PrefabFile = serde_json::from_str(json)?;
+    prefab_file.into_prefab(registry)
+}
+
+pub fn load_prefab_file(path: &Path, registry: &Registry) -> Result<Prefab> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("could not read prefab file {}", path.display()))?;
+    load_prefab_from_json(&json, registry)
+        .with_context(|| format!("could not load prefab file {}", path.display()))
+}
+
+/// Loads every `.json` file in `dir`, sorted by path
+pub fn load_prefab_dir(dir: &Path, registry: &Registry) -> Result<Vec<Prefab>> {
+    get_prefab_paths(dir)?
+        .iter()
+        .map(|path| load_prefab_file(path, registry))
+        .collect()
+}
+
+/// Every `.json` file in `dir`, sorted
+pub fn get_prefab_paths(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("could not read prefab directory {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
@@ -0,0 +1,190 @@
+use super::ecs_errors::EcsErrors;
+use anyhow::Result;
+use std::{any::Any, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReflectValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FieldKind {
+    Bool,
+    Int,
+    Float,
+    String,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct FieldInfo {
+    /// Dotted path to the field, e.g. `position.0` or `color.r`
+    pub path: &'static str,
+    pub kind: FieldKind,
+}
+
+impl FieldInfo {
+    pub const fn new(path: &'static str, kind: FieldKind) -> Self {
+        Self { path, kind }
+    }
+}
+
+impl ReflectValue {
+    /// Parses user input (e.g. a console command argument) into a value of `kind`
+    pub fn parse(kind: FieldKind, field: &str, text: &str) -> Result<Self> {
+        let invalid = || EcsErrors::InvalidFieldValue {
+            field: field.to_string(),
+            value: text.to_string(),
+        };
+        let value = match kind {
+            FieldKind::Bool => ReflectValue::Bool(text.parse().map_err(|_| invalid())?),
+            FieldKind::Int => ReflectValue::Int(text.parse().map_err(|_| invalid())?),
+            FieldKind::Float => ReflectValue::Float(text.parse().map_err(|_| invalid())?),
+            FieldKind::String => ReflectValue::String(text.to_string()),
+        };
+        Ok(value)
+    }
+
+    pub fn get_kind(&self) -> FieldKind {
+        match self {
+            ReflectValue::Bool(_) => FieldKind::Bool,
+            ReflectValue::Int(_) => FieldKind::Int,
+            ReflectValue::Float(_) => FieldKind::Float,
+            ReflectValue::String(_) => FieldKind::String,
+        }
+    }
+
+    pub fn as_bool(&self, field: &str) -> Result<bool> {
+        match self {
+            ReflectValue::Bool(value) => Ok(*value),
+            _ => Err(type_mismatch(field, FieldKind::Bool)),
+        }
+    }
+
+    pub fn as_int(&self, field: &str) -> Result<i64> {
+        match self {
+            ReflectValue::Int(value) => Ok(*value),
+            _ => Err(type_mismatch(field, FieldKind::Int)),
+        }
+    }
+
+    /// Int values are accepted too so `set position.0 10` works
+    pub fn as_float(&self, field: &str) -> Result<f64> {
+        match self {
+            ReflectValue::Float(value) => Ok(*value),
+            ReflectValue::Int(value) => Ok(*value as f64),
+            _ => Err(type_mismatch(field, FieldKind::Float)),
+        }
+    }
+
+    pub fn as_string(&self, field: &str) -> Result<String> {
+        match self {
+            ReflectValue::String(value) => Ok(value.clone()),
+            _ => Err(type_mismatch(field, FieldKind::String)),
+        }
+    }
+
+    /// Range checked conversion for the narrower integer fields (u8 colors, u32 sizes...)
+    pub fn as_int_of<T: TryFrom<i64>>(&self, field: &str) -> Result<T> {
+        T::try_from(self.as_int(field)?).map_err(|_| {
+            EcsErrors::InvalidFieldValue {
+                field: field.to_string(),
+                value: self.to_string(),
+            }
+            .into()
+        })
+    }
+}
+
+impl fmt::Display for ReflectValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectValue::Bool(value) => write!(f, "{}", value),
+            ReflectValue::Int(value) => write!(f, "{}", value),
+            ReflectValue::Float(value) => write!(f, "{}", value),
+            ReflectValue::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+fn type_mismatch(field: &str, expected: FieldKind) -> anyhow::Error {
+    EcsErrors::FieldTypeMismatch {
+        field: field.to_string(),
+        expected,
+    }
+    .into()
+}
+
+/// Field level access to a component, used by the inspector and the console `set` command.
+/// Only scalar fields are reflected, collections (animation clips, tile layers, particles,
+/// shape points) are not. Every component in `components` implements it except
+/// NameComponent, which has to go through Registry::set_entity_name to keep the name
+/// index right, and ParentComponent.
+pub trait Reflect: Any {
+    const FIELDS: &'static [FieldInfo];
+
+    fn get_field(&self, path: &str) -> Option<ReflectValue>;
+
+    fn set_field(&mut self, path: &str, value: ReflectValue) -> Result<()>;
+}
+
+pub fn field_does_not_exist<T: Any>(path: &str) -> anyhow::Error {
+    EcsErrors::FieldDoesNotExist {
+        component: short_type_name::<T>().to_string(),
+        field: path.to_string(),
+    }
+    .into()
+}
+
+/// Type erased `Reflect` functions, captured by `Registry::register_component_reflection`
+#[derive(Clone, Copy)]
+pub struct ComponentReflection {
+    pub fields: &'static [FieldInfo],
+    pub get_field: fn(&dyn Any, &str) -> Option<ReflectValue>,
+    pub set_field: fn(&mut dyn Any, &str, ReflectValue) -> Result<()>,
+}
+
+impl ComponentReflection {
+    pub fn of<T: Reflect>() -> Self {
+        Self {
+            fields: T::FIELDS,
+            get_field: get_component_field::<T>,
+            set_field: set_component_field::<T>,
+        }
+    }
+}
+
+fn get_component_field<T: Reflect>(data: &dyn Any, path: &str) -> Option<ReflectValue> {
+    data.downcast_ref::<T>()?.get_field(path)
+}
+
+fn set_component_field<T: Reflect>(
+    data: &mut dyn Any,
+    path: &str,
+    value: ReflectValue,
+) -> Result<()> {
+    data.downcast_mut::<T>()
+        .ok_or_else(|| EcsErrors::ComponentDoesNotExist(short_type_name::<T>().to_string()))?
+        .set_field(path, value)
+}
+
+/// Runtime information recorded for every registered component type
+pub struct ComponentType {
+    /// `std::any::type_name`, only meant for debugging
+    pub type_name: &'static str,
+    /// Name used in logs, errors, level files and the console
+    pub name: String,
+    pub reflection: Option<ComponentReflection>,
+}
+
+/// `std::any::type_name` without the module path, `engine::ecs::components::TransformComponent`
+/// becomes `TransformComponent`. Generic types are kept as they are.
+pub fn short_type_name<T: ?Sized>() -> &'static str {
+    let type_name = std::any::type_name::<T>();
+    if type_name.contains('<') {
+        return type_name;
+    }
+    type_name.rsplit("::").next().unwrap_or(type_name)
+}
@@ -1,17 +1,33 @@
-use super::ecs_errors::EcsErrors;
+use super::{
+    components::{NameComponent, ParentComponent, TransformComponent},
+    ecs_errors::EcsErrors,
+    prefab::{Prefab, PrefabOverrides},
+    reflect::{
+        short_type_name, ComponentReflection, ComponentType, FieldInfo, Reflect, ReflectValue,
+    },
+    snapshot::{
+        read_entities, read_magic, read_string, read_u16, read_u32, read_u64, read_u8,
+        write_entities, write_string, write_u16, write_u32, write_u64, ComponentSerializer,
+        SchemaHasher, SNAPSHOT_MAGIC, SNAPSHOT_VERSION,
+    },
+};
 use crate::logger::Logger;
 use anyhow::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     any::{Any, TypeId},
     cell::{Ref, RefCell, RefMut},
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     rc::Rc,
 };
 
 pub const MAX_COMPONENTS: usize = 32;
 pub type Component = Rc<RefCell<dyn Any>>;
+pub type Resource = Rc<RefCell<dyn Any>>;
+/// Type erased `Clone`, captured by `Registry::register_component_cloner`
+pub type ComponentCloner = fn(&dyn Any) -> Option<Component>;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Entity(pub usize);
 
 impl Entity {
@@ -20,6 +36,14 @@ impl Entity {
     }
 }
 
+/// What clone_entity does with components that have no cloner registered
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ClonePolicy {
+    #[default]
+    Error,
+    Skip,
+}
+
 #[derive(Default)]
 pub struct Registry {
     logger: Rc<RefCell<Logger>>,
@@ -27,17 +51,34 @@ pub struct Registry {
     /// key => ComponentTypeId, value => Vec<Component>
     components: HashMap<TypeId, Vec<Option<Component>>>,
     component_masks: HashMap<TypeId, u32>,
+    component_serializers: HashMap<TypeId, ComponentSerializer>,
+    component_types: HashMap<TypeId, ComponentType>,
+    component_cloners: HashMap<TypeId, ComponentCloner>,
+    clone_policy: ClonePolicy,
+    /// key => ComponentType::name
+    component_names: HashMap<String, TypeId>,
     /// index: entity_id => signature mask
     entity_masks: Vec<u32>,
 
-    // TODO: no functionality yet
+    /// key => NameComponent value, value => entities with that name
+    entity_names: HashMap<String, BTreeSet<Entity>>,
+    /// key => group name, value => entities in the group
+    groups: BTreeMap<String, BTreeSet<Entity>>,
+
     entities_to_be_added: Vec<Entity>,
     entities_to_be_killed: Vec<Entity>,
     // NOTE: push_back, pop_front (should be Rc<RefCell<VecDeque<Entity>>> as well)
     available_entity_spots: VecDeque<Entity>,
 
     system_masks: Rc<RefCell<HashMap<TypeId, u32>>>,
-    system_entities: Rc<RefCell<HashMap<TypeId, HashSet<Entity>>>>,
+    /// BTreeSet so systems iterate their entities in the same order after a rollback
+    system_entities: Rc<RefCell<HashMap<TypeId, BTreeSet<Entity>>>>,
+    system_names: HashMap<TypeId, &'static str>,
+
+    resources: HashMap<TypeId, Resource>,
+    resource_serializers: HashMap<TypeId, ComponentSerializer>,
+
+    prefabs: HashMap<String, Prefab>,
 }
 
 impl Registry {
@@ -49,88 +90,280 @@ impl Registry {
     }
 
     pub fn update(&mut self) -> Result<()> {
-        // HOW HARD IT CAN BE TO DO A FUCKING NESTED loop in a struct
-        // leftoff here for future refence...
+        self.add_pending_entities_to_systems()?;
 
-        for (system_type_id, system_mask) in self.system_masks.borrow_mut().iter_mut() {
-            for entity in &self.entities_to_be_added {
-                let entity_mask = self.get_entity_mask_mut(*entity).unwrap();
+        for entity in std::mem::take(&mut self.entities_to_be_killed) {
+            self.destroy_entity(entity)?;
+        }
 
-                if *entity_mask & *system_mask == *system_mask {
-                    self.add_entity_to_system_with_id(system_type_id, *entity);
+        Ok(())
+    }
+
+    fn add_pending_entities_to_systems(&mut self) -> Result<()> {
+        let system_masks = self.system_masks.borrow();
+        let mut system_entities = self.system_entities.borrow_mut();
+
+        for entity in self.entities_to_be_added.drain(..) {
+            let entity_mask = self
+                .entity_masks
+                .get(entity.0)
+                .copied()
+                .ok_or(EcsErrors::EntityComponentMaskDoesNotExist)?;
+
+            for (system_type_id, system_mask) in system_masks.iter() {
+                if entity_mask & *system_mask == *system_mask {
+                    system_entities
+                        .get_mut(system_type_id)
+                        .ok_or(EcsErrors::SystemDoesNotExist)?
+                        .insert(entity);
                 }
             }
         }
-        //let to_be_added = self.entities_to_be_added.iter_mut();
-        //for entity in to_be_added{
-        //let entity_mask = self
-        //.get_entity_mask(*entity)
-        //.ok_or(EcsErrors::EntityComponentMaskDoesNotExist)?;
-        //for (system_type_id, system_mask) in self.system_masks.borrow_mut().iter_mut() {
-        //if entity_mask & *system_mask == *system_mask {
-        //self.add_entity_to_system_with_id(system_type_id, *entity);
-        //}
-        //}
-        //}
-
-        self.entities_to_be_added.clear();
 
         Ok(())
     }
 
+    /// The component is named after its type without the module path
     pub fn register_component<T: Any + 'static>(&mut self) -> Result<()> {
+        self.register_named_component::<T>(short_type_name::<T>())
+    }
+
+    /// `name` is what logs, errors and data files use to refer to the component
+    pub fn register_named_component<T: Any + 'static>(&mut self, name: &str) -> Result<()> {
         if self.components.len() >= MAX_COMPONENTS {
             return Err(EcsErrors::MaxComponentReached.into());
         }
+        if self.component_names.contains_key(name) {
+            return Err(EcsErrors::ComponentNameTaken(name.to_string()).into());
+        }
         let type_id = TypeId::of::<T>();
         let components_length = self.components.len();
-        self.components.insert(type_id, vec![]);
+        self.components
+            .insert(type_id, vec![None; self.num_entities]);
         self.component_masks.insert(type_id, 1 << components_length);
+        self.component_names.insert(name.to_string(), type_id);
+        self.component_types.insert(
+            type_id,
+            ComponentType {
+                type_name: std::any::type_name::<T>(),
+                name: name.to_string(),
+                reflection: None,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn register_component_cloner<T: Any + Clone>(&mut self) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+        if !self.components.contains_key(&type_id) {
+            return Err(
+                EcsErrors::ComponentDoesNotExist(short_type_name::<T>().to_string()).into(),
+            );
+        }
+        self.component_cloners.insert(type_id, clone_component::<T>);
+        Ok(())
+    }
+
+    pub fn register_component_reflection<T: Reflect>(&mut self) -> Result<()> {
+        let component_type = self
+            .component_types
+            .get_mut(&TypeId::of::<T>())
+            .ok_or_else(|| EcsErrors::ComponentDoesNotExist(short_type_name::<T>().to_string()))?;
+        component_type.reflection = Some(ComponentReflection::of::<T>());
+        Ok(())
+    }
+
+    /// Components have to be registered first, snapshots fail for entities holding
+    /// a component without a serializer
+    pub fn register_component_serializer<T: Any + Serialize + DeserializeOwned>(
+        &mut self,
+    ) -> Result<()> {
+        self.register_component_serializer_with_version::<T>(0)
+    }
+
+    /// Bump `schema_version` when the serialized fields of T change, snapshots made with
+    /// another version are rejected instead of being misread
+    pub fn register_component_serializer_with_version<T: Any + Serialize + DeserializeOwned>(
+        &mut self,
+        schema_version: u32,
+    ) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+        if !self.components.contains_key(&type_id) {
+            return Err(
+                EcsErrors::ComponentDoesNotExist(short_type_name::<T>().to_string()).into(),
+            );
+        }
+        self.component_serializers.insert(
+            type_id,
+            ComponentSerializer::with_version::<T>(schema_version),
+        );
         Ok(())
     }
 
     pub fn create_entity(&mut self) -> Entity {
-        if self.components.len() == 0 {
+        let entity = self.allocate_entity();
+
+        self.logger.as_ref().borrow_mut().log(&format!(
+            "Entity created with {}",
+            self.get_entity_label(entity)
+        ));
+
+        entity
+    }
+
+    /// NameComponent has to be registered
+    pub fn create_named_entity(&mut self, name: &str) -> Result<Entity> {
+        if self.get_component_mask::<NameComponent>().is_none() {
+            return Err(EcsErrors::ComponentDoesNotExist(
+                short_type_name::<NameComponent>().to_string(),
+            )
+            .into());
+        }
+        let entity = self.allocate_entity();
+        self.add_component(entity, NameComponent(name.to_string()))?;
+
+        self.logger.as_ref().borrow_mut().log(&format!(
+            "Entity created with {}",
+            self.get_entity_label(entity)
+        ));
+
+        Ok(entity)
+    }
+
+    fn allocate_entity(&mut self) -> Entity {
+        if self.components.is_empty() {
             panic!("Register components first and then create entities!");
         }
-        if self.available_entity_spots.is_empty() {
-            let entity = Entity::new(self.num_entities);
-            self.num_entities += 1;
 
-            if !self.entities_to_be_added.contains(&entity) {
-                self.entities_to_be_added.push(entity);
-            }
+        // Killed entities leave their spot with an empty mask and no components
+        let entity = match self.available_entity_spots.pop_front() {
+            Some(entity) => entity,
+            None => {
+                let entity = Entity::new(self.num_entities);
+                self.num_entities += 1;
 
-            // Fill component for all the component types None by default
-            for (_type_id, components_vec) in self.components.iter_mut() {
-                components_vec.push(None);
+                // Fill component for all the component types None by default
+                for (_type_id, components_vec) in self.components.iter_mut() {
+                    components_vec.push(None);
+                }
+
+                self.entity_masks.push(0);
+                entity
             }
+        };
 
-            self.entity_masks.push(0);
+        if !self.entities_to_be_added.contains(&entity) {
+            self.entities_to_be_added.push(entity);
+        }
 
-            self.logger
-                .as_ref()
-                .borrow_mut()
-                .log(&format!("Entity created with id = {}", entity.0));
+        entity
+    }
 
-            entity
-        } else {
-            todo!();
+    /// The entity is removed from its systems and its spot becomes available on the
+    /// next `update`. Children (entities whose ParentComponent points at it) are killed too.
+    pub fn kill_entity(&mut self, entity: Entity) -> Result<()> {
+        if self.get_entity_mask(entity).is_none() || self.available_entity_spots.contains(&entity) {
+            return Err(EcsErrors::EntityDoesNotExist(entity.0).into());
+        }
+        if self.entities_to_be_killed.contains(&entity) {
+            return Ok(());
+        }
+        self.entities_to_be_killed.push(entity);
+
+        self.logger.as_ref().borrow_mut().log(&format!(
+            "Entity {} was killed",
+            self.get_entity_label(entity)
+        ));
+
+        for child in self.get_children(entity) {
+            self.kill_entity(child)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_parent(&self, entity: Entity) -> Option<Entity> {
+        if !self.entity_has_component::<ParentComponent>(entity) {
+            return None;
+        }
+        let parent = self.get_component::<ParentComponent>(entity).ok()?;
+        Some(parent.0)
+    }
+
+    pub fn get_children(&self, entity: Entity) -> Vec<Entity> {
+        self.get_entities_with::<ParentComponent>()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|child| self.get_parent(*child) == Some(entity))
+            .collect()
+    }
+
+    fn destroy_entity(&mut self, entity: Entity) -> Result<()> {
+        for entities in self.system_entities.borrow_mut().values_mut() {
+            entities.remove(&entity);
         }
+
+        self.unindex_entity_name(entity);
+        self.remove_entity_from_all_groups(entity);
+
+        for components_vec in self.components.values_mut() {
+            if let Some(component) = components_vec.get_mut(entity.0) {
+                *component = None;
+            }
+        }
+
+        let entity_mask = self
+            .get_entity_mask_mut(entity)
+            .ok_or(EcsErrors::EntityComponentMaskDoesNotExist)?;
+        *entity_mask = 0;
+
+        self.available_entity_spots.push_back(entity);
+        Ok(())
     }
 
     // Component management
     /// NOTE: If you add the same component again it will override
     pub fn add_component(&mut self, entity: Entity, data: impl Any) -> Result<()> {
-        let entity_id = entity.0;
         let component_type_id = data.type_id();
+        if !self.components.contains_key(&component_type_id) {
+            return Err(EcsErrors::ComponentDoesNotExist(
+                std::any::type_name_of_val(&data).to_string(),
+            )
+            .into());
+        }
+        self.add_component_with_id(entity, component_type_id, Rc::new(RefCell::new(data)))
+    }
 
+    /// Type erased add_component, `component` has to hold a value of type `component_type_id`
+    pub(crate) fn add_component_with_id(
+        &mut self,
+        entity: Entity,
+        component_type_id: TypeId,
+        component: Component,
+    ) -> Result<()> {
+        if (*component.borrow()).type_id() != component_type_id {
+            return Err(EcsErrors::ComponentTypeMismatch(
+                self.get_component_label(component_type_id),
+            )
+            .into());
+        }
+        let entity_id = entity.0;
+
+        let name = component
+            .borrow()
+            .downcast_ref::<NameComponent>()
+            .map(|name| name.0.clone());
+        if name.is_some() {
+            self.unindex_entity_name(entity);
+        }
+
+        let component_label = self.get_component_label(component_type_id);
         let component_vec = self
             .components
             .get_mut(&component_type_id)
-            .ok_or(EcsErrors::ComponentDoesNotExist)?;
+            .ok_or(EcsErrors::ComponentDoesNotExist(component_label))?;
 
-        component_vec[entity_id] = Some(Rc::new(RefCell::new(data)));
+        component_vec[entity_id] = Some(component);
 
         let component_mask = self
             .get_component_mask_with_id(component_type_id)
@@ -142,6 +375,10 @@ impl Registry {
             return Err(EcsErrors::EntityComponentMaskDoesNotExist.into());
         }
 
+        if let Some(name) = name {
+            self.index_entity_name(entity, name);
+        }
+
         Ok(())
     }
 
@@ -151,6 +388,10 @@ impl Registry {
             .ok_or(EcsErrors::ComponentMaskDoesNotExist)?;
 
         if self.has_component_with_mask(entity, component_mask)? {
+            if TypeId::of::<T>() == TypeId::of::<NameComponent>() {
+                self.unindex_entity_name(entity);
+            }
+
             let entity_mask = self
                 .get_entity_mask_mut(entity)
                 .ok_or(EcsErrors::EntityComponentMaskDoesNotExist)?;
@@ -158,9 +399,9 @@ impl Registry {
             *entity_mask ^= component_mask;
 
             self.logger.as_ref().borrow_mut().log(&format!(
-                "Component id = {:?} was removed from entity id {}",
-                &TypeId::of::<T>(),
-                entity.0
+                "Component {} was removed from entity {}",
+                self.get_component_label(TypeId::of::<T>()),
+                self.get_entity_label(entity)
             ));
             return Ok(true);
         }
@@ -179,6 +420,11 @@ impl Registry {
         Ok(entity_mask & component_mask == component_mask)
     }
 
+    /// False for entities that do not exist and component types that are not registered
+    pub fn entity_has_component<T: Any>(&self, entity: Entity) -> bool {
+        self.has_component::<T>(entity).unwrap_or(false)
+    }
+
     pub fn has_component_with_mask(&self, entity: Entity, component_mask: u32) -> Result<bool> {
         let entity_mask = self
             .get_entity_mask(entity)
@@ -191,46 +437,621 @@ impl Registry {
         let components = self
             .components
             .get(&type_id)
-            .ok_or(EcsErrors::ComponentDoesNotExist)?;
+            .ok_or_else(|| EcsErrors::ComponentDoesNotExist(short_type_name::<T>().to_string()))?;
         Ok(components)
     }
 
-    pub fn get_component<T: Any>(&self, entity: Entity) -> Result<Ref<T>> {
+    pub fn get_component<T: Any>(&self, entity: Entity) -> Result<Ref<'_, T>> {
         if !self.has_component::<T>(entity)? {
-            return Err(EcsErrors::ComponentDoesNotExist.into());
+            return Err(self.missing_component_error(entity, TypeId::of::<T>()));
         }
         let components = self.extract_components::<T>()?;
         let borrowed_component = components[entity.0]
             .as_ref()
-            .ok_or(EcsErrors::ComponentDoesNotExist)?
+            .ok_or_else(|| self.missing_component_error(entity, TypeId::of::<T>()))?
             .borrow();
         Ok(Ref::map(borrowed_component, |any| {
             any.downcast_ref::<T>().unwrap()
         }))
     }
 
-    pub fn get_component_mut<T: Any + 'static>(&self, entity: Entity) -> Result<RefMut<T>> {
+    pub fn get_component_mut<T: Any + 'static>(&self, entity: Entity) -> Result<RefMut<'_, T>> {
         if !self.has_component::<T>(entity)? {
-            return Err(EcsErrors::ComponentDoesNotExist.into());
+            return Err(self.missing_component_error(entity, TypeId::of::<T>()));
         }
         let components = self.extract_components::<T>()?;
         let borrowed_component = components[entity.0]
             .as_ref()
-            .ok_or(EcsErrors::ComponentDoesNotExist)?
+            .ok_or_else(|| self.missing_component_error(entity, TypeId::of::<T>()))?
             .borrow_mut();
         Ok(RefMut::map(borrowed_component, |any| {
             any.downcast_mut::<T>().unwrap()
         }))
     }
 
+    fn missing_component_error(&self, entity: Entity, type_id: TypeId) -> anyhow::Error {
+        EcsErrors::EntityDoesNotHaveComponent {
+            entity: self.get_entity_label(entity),
+            component: self.get_component_label(type_id),
+        }
+        .into()
+    }
+
+    /// How an entity is shown in logs and errors
+    pub fn get_entity_label(&self, entity: Entity) -> String {
+        match self.get_entity_name(entity) {
+            Some(name) => format!("\"{}\" (id = {})", name, entity.0),
+            None => format!("id = {}", entity.0),
+        }
+    }
+
+    // Cloning
+    pub fn set_clone_policy(&mut self, clone_policy: ClonePolicy) {
+        self.clone_policy = clone_policy;
+    }
+
+    pub fn get_clone_policy(&self) -> ClonePolicy {
+        self.clone_policy
+    }
+
+    /// Creates a new entity with copies of the entity's components and the same groups.
+    /// Components without a cloner fail the whole clone or are left out, depending on the
+    /// clone policy. Children are not cloned.
+    pub fn clone_entity(&mut self, entity: Entity) -> Result<Entity> {
+        let entity_mask = self
+            .get_entity_mask(entity)
+            .ok_or(EcsErrors::EntityComponentMaskDoesNotExist)?;
+
+        let mut components = vec![];
+        for (type_id, component_mask) in self.get_component_ids_by_mask() {
+            if entity_mask & component_mask != component_mask {
+                continue;
+            }
+            match self.clone_component_with_id(entity, type_id) {
+                Ok(component) => components.push((type_id, component)),
+                Err(err)
+                    if self.clone_policy == ClonePolicy::Skip
+                        && matches!(
+                            err.downcast_ref::<EcsErrors>(),
+                            Some(EcsErrors::ComponentNotCloneable(_))
+                        ) =>
+                {
+                    continue
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let clone = self.allocate_entity();
+        for (type_id, component) in components {
+            self.add_component_with_id(clone, type_id, component)?;
+        }
+        for group in self
+            .get_entity_groups(entity)
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+        {
+            self.add_entity_to_group(clone, &group)?;
+        }
+
+        self.logger.as_ref().borrow_mut().log(&format!(
+            "Entity {} was cloned into {}",
+            self.get_entity_label(entity),
+            self.get_entity_label(clone)
+        ));
+
+        Ok(clone)
+    }
+
+    /// NOTE: Overrides the component if `to` already has one
+    pub fn copy_component<T: Any>(&mut self, from: Entity, to: Entity) -> Result<()> {
+        self.copy_component_with_id(TypeId::of::<T>(), from, to)
+    }
+
+    pub fn copy_component_with_id(
+        &mut self,
+        type_id: TypeId,
+        from: Entity,
+        to: Entity,
+    ) -> Result<()> {
+        let component = self.clone_component_with_id(from, type_id)?;
+        self.add_component_with_id(to, type_id, component)
+    }
+
+    fn clone_component_with_id(&self, entity: Entity, type_id: TypeId) -> Result<Component> {
+        let cloner = self
+            .component_cloners
+            .get(&type_id)
+            .ok_or_else(|| EcsErrors::ComponentNotCloneable(self.get_component_label(type_id)))?;
+
+        let component_mask = self
+            .get_component_mask_with_id(type_id)
+            .ok_or_else(|| EcsErrors::ComponentDoesNotExist(self.get_component_label(type_id)))?;
+        if !self.has_component_with_mask(entity, component_mask)? {
+            return Err(self.missing_component_error(entity, type_id));
+        }
+
+        let component = self.components[&type_id][entity.0]
+            .as_ref()
+            .ok_or_else(|| self.missing_component_error(entity, type_id))?;
+        let clone = cloner(&*component.borrow())
+            .ok_or_else(|| EcsErrors::ComponentNotCloneable(self.get_component_label(type_id)))?;
+        Ok(clone)
+    }
+
+    // Moving entities between registries
+    /// Moves the entity and its children into `destination`. Returns old id => new id.
+    pub fn move_entity(
+        &mut self,
+        entity: Entity,
+        destination: &mut Registry,
+    ) -> Result<HashMap<Entity, Entity>> {
+        self.move_entities(&[entity], destination)
+    }
+
+    /// Components are moved as they are, without cloning, so `destination` has to register
+    /// the same component types. Names and groups go along with the entities and
+    /// ParentComponents pointing outside of the moved entities are dropped.
+    /// NOTE: Killed entities can not be moved, killed children stay behind and are
+    /// destroyed by the next update
+    pub fn move_entities(
+        &mut self,
+        entities: &[Entity],
+        destination: &mut Registry,
+    ) -> Result<HashMap<Entity, Entity>> {
+        if let Some(entity) = entities
+            .iter()
+            .find(|entity| self.entities_to_be_killed.contains(entity))
+        {
+            return Err(EcsErrors::EntityIsKilled(entity.0).into());
+        }
+
+        let mut moved = BTreeSet::new();
+        let mut pending = entities.to_vec();
+        while let Some(entity) = pending.pop() {
+            if self.get_entity_mask(entity).is_none()
+                || self.available_entity_spots.contains(&entity)
+            {
+                return Err(EcsErrors::EntityDoesNotExist(entity.0).into());
+            }
+            if moved.insert(entity) {
+                pending.extend(
+                    self.get_children(entity)
+                        .into_iter()
+                        .filter(|child| !self.entities_to_be_killed.contains(child)),
+                );
+            }
+        }
+
+        // Validate everything before touching either registry
+        let component_ids = self.get_component_ids_by_mask();
+        for &entity in &moved {
+            let entity_mask = self.entity_masks[entity.0];
+            for (type_id, component_mask) in &component_ids {
+                if entity_mask & component_mask == *component_mask
+                    && !destination.components.contains_key(type_id)
+                {
+                    return Err(EcsErrors::ComponentDoesNotExist(
+                        self.get_component_label(*type_id),
+                    )
+                    .into());
+                }
+            }
+        }
+
+        let entity_map: HashMap<Entity, Entity> = moved
+            .iter()
+            .map(|&entity| (entity, destination.allocate_entity()))
+            .collect();
+
+        let parent_type_id = TypeId::of::<ParentComponent>();
+        for &entity in &moved {
+            let new_entity = entity_map[&entity];
+            let entity_mask = self.entity_masks[entity.0];
+
+            for (type_id, component_mask) in &component_ids {
+                if entity_mask & component_mask != *component_mask {
+                    continue;
+                }
+                let Some(component) = self.components[type_id][entity.0].clone() else {
+                    continue;
+                };
+                if *type_id == parent_type_id {
+                    let parent = self.get_component::<ParentComponent>(entity)?.0;
+                    let Some(new_parent) = entity_map.get(&parent) else {
+                        continue;
+                    };
+                    destination.add_component(new_entity, ParentComponent(*new_parent))?;
+                    continue;
+                }
+                destination.add_component_with_id(new_entity, *type_id, component)?;
+            }
+
+            for group in self.get_entity_groups(entity) {
+                destination.add_entity_to_group(new_entity, group)?;
+            }
+
+            self.logger.as_ref().borrow_mut().log(&format!(
+                "Entity {} was moved to another registry as {}",
+                self.get_entity_label(entity),
+                destination.get_entity_label(new_entity)
+            ));
+
+            self.entities_to_be_added
+                .retain(|pending| *pending != entity);
+            self.destroy_entity(entity)?;
+        }
+
+        Ok(entity_map)
+    }
+
+    // Prefabs
+    /// NOTE: Registering a prefab with the same name again replaces it
+    pub fn register_prefab(&mut self, prefab: Prefab) {
+        self.prefabs.insert(prefab.name.clone(), prefab);
+    }
+
+    pub fn get_prefab(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Creates the prefab's root entity (with `overrides` replacing its components) and
+    /// its children, returns the root
+    pub fn instantiate_prefab(
+        &mut self,
+        name: &str,
+        overrides: &PrefabOverrides,
+    ) -> Result<Entity> {
+        let prefab = self
+            .prefabs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| EcsErrors::PrefabDoesNotExist(name.to_string()))?;
+
+        let root = self.instantiate_prefab_entity(&prefab, overrides, None)?;
+
+        self.logger.as_ref().borrow_mut().log(&format!(
+            "Prefab {} instantiated as entity {}",
+            name,
+            self.get_entity_label(root)
+        ));
+
+        Ok(root)
+    }
+
+    fn instantiate_prefab_entity(
+        &mut self,
+        prefab: &Prefab,
+        overrides: &PrefabOverrides,
+        parent: Option<Entity>,
+    ) -> Result<Entity> {
+        let entity = self.allocate_entity();
+
+        for (type_id, factory) in prefab.get_components() {
+            let factory = overrides.get(*type_id).unwrap_or(factory);
+            self.add_component_with_id(entity, *type_id, factory()?)?;
+        }
+        for (type_id, factory) in overrides.get_components() {
+            if !prefab.get_components().iter().any(|(id, _)| id == type_id) {
+                self.add_component_with_id(entity, *type_id, factory()?)?;
+            }
+        }
+
+        if let Some(parent) = parent {
+            self.add_component(entity, ParentComponent(parent))?;
+
+            if self.entity_has_component::<TransformComponent>(entity)
+                && self.entity_has_component::<TransformComponent>(parent)
+            {
+                let parent_position = self.get_component::<TransformComponent>(parent)?.position;
+                let mut transform = self.get_component_mut::<TransformComponent>(entity)?;
+                transform.position.0 += parent_position.0;
+                transform.position.1 += parent_position.1;
+            }
+        }
+
+        for child in prefab.get_children() {
+            self.instantiate_prefab_entity(child, &PrefabOverrides::new(), Some(entity))?;
+        }
+
+        Ok(entity)
+    }
+
+    // Entity names
+    pub fn get_entity_name(&self, entity: Entity) -> Option<String> {
+        if !self.entity_has_component::<NameComponent>(entity) {
+            return None;
+        }
+        let name = self.get_component::<NameComponent>(entity).ok()?;
+        Some(name.0.clone())
+    }
+
+    /// Renaming has to go through here (or add_component) to keep the name index correct
+    pub fn set_entity_name(&mut self, entity: Entity, name: &str) -> Result<()> {
+        self.add_component(entity, NameComponent(name.to_string()))
+    }
+
+    /// If several entities share the name the one with the lowest id is returned
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.entity_names
+            .get(name)
+            .and_then(|entities| entities.first().copied())
+    }
+
+    pub fn find_all_by_name(&self, name: &str) -> Vec<Entity> {
+        self.entity_names
+            .get(name)
+            .map(|entities| entities.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn index_entity_name(&mut self, entity: Entity, name: String) {
+        self.entity_names.entry(name).or_default().insert(entity);
+    }
+
+    fn unindex_entity_name(&mut self, entity: Entity) {
+        let Some(name) = self.get_entity_name(entity) else {
+            return;
+        };
+        if let Some(entities) = self.entity_names.get_mut(&name) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                self.entity_names.remove(&name);
+            }
+        }
+    }
+
+    fn rebuild_name_index(&mut self) {
+        self.entity_names.clear();
+        for entity_id in 0..self.num_entities {
+            let entity = Entity::new(entity_id);
+            if let Some(name) = self.get_entity_name(entity) {
+                self.index_entity_name(entity, name);
+            }
+        }
+    }
+
+    /// How a component type is shown in logs and errors, falls back to the TypeId debug
+    /// output for types that are not registered
+    pub fn get_component_label(&self, type_id: TypeId) -> String {
+        match self.component_types.get(&type_id) {
+            Some(component_type) => component_type.name.clone(),
+            None => format!("{:?}", type_id),
+        }
+    }
+
+    /// Living entities whose mask contains every bit of `mask`. Pass a tag component's
+    /// mask to iterate everything with that tag.
+    pub fn get_entities_with_mask(&self, mask: u32) -> Vec<Entity> {
+        if mask == 0 {
+            return vec![];
+        }
+        self.entity_masks
+            .iter()
+            .enumerate()
+            .filter(|(_, entity_mask)| *entity_mask & mask == mask)
+            .map(|(entity_id, _)| Entity::new(entity_id))
+            .collect()
+    }
+
+    pub fn get_entities_with<T: Any>(&self) -> Result<Vec<Entity>> {
+        let component_mask = self
+            .get_component_mask::<T>()
+            .ok_or(EcsErrors::ComponentMaskDoesNotExist)?;
+        Ok(self.get_entities_with_mask(component_mask))
+    }
+
+    // Groups
+    pub fn add_entity_to_group(&mut self, entity: Entity, group: &str) -> Result<()> {
+        if self.get_entity_mask(entity).is_none() {
+            return Err(EcsErrors::EntityDoesNotExist(entity.0).into());
+        }
+        self.groups
+            .entry(group.to_string())
+            .or_default()
+            .insert(entity);
+        Ok(())
+    }
+
+    pub fn remove_entity_from_group(&mut self, entity: Entity, group: &str) -> bool {
+        let Some(entities) = self.groups.get_mut(group) else {
+            return false;
+        };
+        let removed = entities.remove(&entity);
+        if entities.is_empty() {
+            self.groups.remove(group);
+        }
+        removed
+    }
+
+    fn remove_entity_from_all_groups(&mut self, entity: Entity) {
+        self.groups.retain(|_, entities| {
+            entities.remove(&entity);
+            !entities.is_empty()
+        });
+    }
+
+    pub fn entity_belongs_to_group(&self, entity: Entity, group: &str) -> bool {
+        self.groups
+            .get(group)
+            .is_some_and(|entities| entities.contains(&entity))
+    }
+
+    /// Entities are returned in id order
+    pub fn get_group_entities(&self, group: &str) -> impl Iterator<Item = Entity> + '_ {
+        self.groups.get(group).into_iter().flatten().copied()
+    }
+
+    pub fn get_group_size(&self, group: &str) -> usize {
+        self.groups.get(group).map_or(0, |entities| entities.len())
+    }
+
+    pub fn get_entity_groups(&self, entity: Entity) -> Vec<&str> {
+        self.groups
+            .iter()
+            .filter(|(_, entities)| entities.contains(&entity))
+            .map(|(group, _)| group.as_str())
+            .collect()
+    }
+
+    // Type registry
+    pub fn get_component_serializer(&self, type_id: TypeId) -> Option<&ComponentSerializer> {
+        self.component_serializers.get(&type_id)
+    }
+
+    pub fn get_component_type(&self, type_id: TypeId) -> Option<&ComponentType> {
+        self.component_types.get(&type_id)
+    }
+
+    pub fn get_component_type_id(&self, name: &str) -> Option<TypeId> {
+        self.component_names.get(name).copied()
+    }
+
+    pub fn get_component_name<T: Any>(&self) -> Option<&str> {
+        self.component_types
+            .get(&TypeId::of::<T>())
+            .map(|component_type| component_type.name.as_str())
+    }
+
+    /// Registered component names sorted by their mask bit
+    pub fn get_component_names(&self) -> Vec<&str> {
+        self.get_component_ids_by_mask()
+            .into_iter()
+            .filter_map(|(type_id, _)| self.component_types.get(&type_id))
+            .map(|component_type| component_type.name.as_str())
+            .collect()
+    }
+
+    /// Names of the components the entity has, for the inspector
+    pub fn get_entity_component_names(&self, entity: Entity) -> Result<Vec<&str>> {
+        let entity_mask = self
+            .get_entity_mask(entity)
+            .ok_or(EcsErrors::EntityComponentMaskDoesNotExist)?;
+        Ok(self
+            .get_component_ids_by_mask()
+            .into_iter()
+            .filter(|(_, component_mask)| entity_mask & component_mask == *component_mask)
+            .filter_map(|(type_id, _)| self.component_types.get(&type_id))
+            .map(|component_type| component_type.name.as_str())
+            .collect())
+    }
+
+    fn get_reflected_component(
+        &self,
+        entity: Entity,
+        component_name: &str,
+    ) -> Result<(ComponentReflection, &Component)> {
+        let type_id = self
+            .get_component_type_id(component_name)
+            .ok_or_else(|| EcsErrors::ComponentDoesNotExist(component_name.to_string()))?;
+        let reflection = self.component_types[&type_id]
+            .reflection
+            .ok_or_else(|| EcsErrors::ComponentNotReflectable(component_name.to_string()))?;
+
+        let component_mask = self
+            .get_component_mask_with_id(type_id)
+            .ok_or(EcsErrors::ComponentMaskDoesNotExist)?;
+        if !self.has_component_with_mask(entity, component_mask)? {
+            return Err(self.missing_component_error(entity, type_id));
+        }
+        let component = self.components[&type_id][entity.0]
+            .as_ref()
+            .ok_or_else(|| self.missing_component_error(entity, type_id))?;
+        Ok((reflection, component))
+    }
+
+    pub fn get_component_fields(&self, component_name: &str) -> Result<&'static [FieldInfo]> {
+        let type_id = self
+            .get_component_type_id(component_name)
+            .ok_or_else(|| EcsErrors::ComponentDoesNotExist(component_name.to_string()))?;
+        let reflection = self.component_types[&type_id]
+            .reflection
+            .ok_or_else(|| EcsErrors::ComponentNotReflectable(component_name.to_string()))?;
+        Ok(reflection.fields)
+    }
+
+    pub fn get_component_field(
+        &self,
+        entity: Entity,
+        component_name: &str,
+        path: &str,
+    ) -> Result<ReflectValue> {
+        let (reflection, component) = self.get_reflected_component(entity, component_name)?;
+        let value = (reflection.get_field)(&*component.borrow(), path).ok_or_else(|| {
+            EcsErrors::FieldDoesNotExist {
+                component: component_name.to_string(),
+                field: path.to_string(),
+            }
+        })?;
+        Ok(value)
+    }
+
+    pub fn set_component_field(
+        &self,
+        entity: Entity,
+        component_name: &str,
+        path: &str,
+        value: ReflectValue,
+    ) -> Result<()> {
+        let (reflection, component) = self.get_reflected_component(entity, component_name)?;
+        if !reflection.fields.iter().any(|field| field.path == path) {
+            return Err(EcsErrors::FieldDoesNotExist {
+                component: component_name.to_string(),
+                field: path.to_string(),
+            }
+            .into());
+        }
+        let result = (reflection.set_field)(&mut *component.borrow_mut(), path, value);
+        result
+    }
+
+    /// Parses `text` according to the field's kind, e.g. `set player TransformComponent position.0 10`
+    pub fn set_component_field_from_str(
+        &self,
+        entity: Entity,
+        component_name: &str,
+        path: &str,
+        text: &str,
+    ) -> Result<()> {
+        let field = self
+            .get_component_fields(component_name)?
+            .iter()
+            .find(|field| field.path == path)
+            .ok_or_else(|| EcsErrors::FieldDoesNotExist {
+                component: component_name.to_string(),
+                field: path.to_string(),
+            })?;
+        let value = ReflectValue::parse(field.kind, path, text)?;
+        self.set_component_field(entity, component_name, path, value)
+    }
+
     pub fn get_num_entities(&self) -> usize {
         self.num_entities
     }
 
+    /// Entities created since the last update, in creation order
+    pub fn get_pending_entities(&self) -> &[Entity] {
+        &self.entities_to_be_added
+    }
+
     pub fn get_num_systems(&self) -> usize {
         self.system_masks.borrow().len()
     }
 
+    /// (system name, number of entities) for every system, sorted by name
+    pub fn get_system_entity_counts(&self) -> Vec<(&'static str, usize)> {
+        let system_entities = self.system_entities.borrow();
+        self.get_system_ids_by_name()
+            .into_iter()
+            .map(|(type_id, name)| {
+                let name = name.rsplit("::").next().unwrap_or(name);
+                let count = system_entities.get(&type_id).map_or(0, BTreeSet::len);
+                (name, count)
+            })
+            .collect()
+    }
+
     pub fn get_entity_mask(&self, entity: Entity) -> Option<u32> {
         self.entity_masks.get(entity.0).copied()
     }
@@ -253,13 +1074,32 @@ impl Registry {
             return Ok(false);
         }
         self.system_masks.borrow_mut().insert(type_id, system_mask); // this wrong
-        self.system_entities
-            .borrow_mut()
-            .insert(type_id, HashSet::new());
+
+        // Entities that are already in their systems join this one too, pending entities
+        // join all of their systems with the next update
+        let entities: BTreeSet<Entity> = self
+            .entity_masks
+            .iter()
+            .enumerate()
+            .map(|(entity_id, entity_mask)| (Entity::new(entity_id), *entity_mask))
+            .filter(|(entity, entity_mask)| {
+                entity_mask & system_mask == system_mask
+                    && !self.available_entity_spots.contains(entity)
+                    && !self.entities_to_be_added.contains(entity)
+            })
+            .map(|(entity, _)| entity)
+            .collect();
+        self.system_entities.borrow_mut().insert(type_id, entities);
+        self.system_names
+            .insert(type_id, std::any::type_name::<T>());
         Ok(true)
     }
 
-    pub fn get_system_entities<T: Any>(&self) -> Result<HashSet<Entity>> {
+    pub fn has_system<T: Any>(&self) -> bool {
+        self.system_masks.borrow().contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get_system_entities<T: Any>(&self) -> Result<BTreeSet<Entity>> {
         let type_id = TypeId::of::<T>();
         let borrowed_entities = self.system_entities.borrow();
         let entities = borrowed_entities
@@ -284,7 +1124,7 @@ impl Registry {
     pub fn add_entity_to_system_with_id(&mut self, type_id: &TypeId, entity: Entity) -> Result<()> {
         let mut borrowed_entities = self.system_entities.borrow_mut();
         let entities = borrowed_entities
-            .get_mut(&type_id)
+            .get_mut(type_id)
             .ok_or(EcsErrors::SystemDoesNotExist)?;
         entities.insert(entity);
         Ok(())
@@ -307,7 +1147,7 @@ impl Registry {
     ) -> Result<()> {
         let mut borrowed_entities = self.system_entities.borrow_mut();
         let entities = borrowed_entities
-            .get_mut(&type_id)
+            .get_mut(type_id)
             .ok_or(EcsErrors::SystemDoesNotExist)?;
         entities.remove(&entity);
         Ok(())
@@ -322,16 +1162,540 @@ impl Registry {
             .ok_or(EcsErrors::SystemDoesNotExist)?;
         Ok(mask)
     }
+
+    // Resource management
+    /// NOTE: If you add the same resource again it will override
+    pub fn add_resource(&mut self, data: impl Any) {
+        self.resources
+            .insert(data.type_id(), Rc::new(RefCell::new(data)));
+    }
+
+    pub fn remove_resource<T: Any>(&mut self) -> bool {
+        self.resources.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    pub fn has_resource<T: Any>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get_resource<T: Any>(&self) -> Result<Ref<'_, T>> {
+        let borrowed_resource = self
+            .resources
+            .get(&TypeId::of::<T>())
+            .ok_or(EcsErrors::ResourceDoesNotExist)?
+            .borrow();
+        Ok(Ref::map(borrowed_resource, |any| {
+            any.downcast_ref::<T>().unwrap()
+        }))
+    }
+
+    pub fn get_resource_mut<T: Any>(&self) -> Result<RefMut<'_, T>> {
+        let borrowed_resource = self
+            .resources
+            .get(&TypeId::of::<T>())
+            .ok_or(EcsErrors::ResourceDoesNotExist)?
+            .borrow_mut();
+        Ok(RefMut::map(borrowed_resource, |any| {
+            any.downcast_mut::<T>().unwrap()
+        }))
+    }
+
+    /// Only resources with a serializer are part of snapshots
+    pub fn register_resource_serializer<T: Any + Serialize + DeserializeOwned>(&mut self) {
+        self.register_resource_serializer_with_version::<T>(0);
+    }
+
+    /// See `register_component_serializer_with_version`
+    pub fn register_resource_serializer_with_version<T: Any + Serialize + DeserializeOwned>(
+        &mut self,
+        schema_version: u32,
+    ) {
+        self.resource_serializers.insert(
+            TypeId::of::<T>(),
+            ComponentSerializer::with_version::<T>(schema_version),
+        );
+    }
+
+    // Snapshots
+    /// Component type ids ordered by their mask bit, columns are written in this order
+    fn get_component_ids_by_mask(&self) -> Vec<(TypeId, u32)> {
+        let mut component_ids: Vec<(TypeId, u32)> = self
+            .component_masks
+            .iter()
+            .map(|(type_id, mask)| (*type_id, *mask))
+            .collect();
+        component_ids.sort_by_key(|(_, mask)| *mask);
+        component_ids
+    }
+
+    fn get_resource_serializers_by_name(&self) -> Vec<(TypeId, ComponentSerializer)> {
+        let mut resource_serializers: Vec<(TypeId, ComponentSerializer)> = self
+            .resource_serializers
+            .iter()
+            .map(|(type_id, serializer)| (*type_id, *serializer))
+            .collect();
+        resource_serializers.sort_by_key(|(_, serializer)| serializer.type_name);
+        resource_serializers
+    }
+
+    fn get_system_ids_by_name(&self) -> Vec<(TypeId, &'static str)> {
+        let mut system_ids: Vec<(TypeId, &'static str)> = self
+            .system_names
+            .iter()
+            .map(|(type_id, name)| (*type_id, *name))
+            .collect();
+        system_ids.sort_by_key(|(_, name)| *name);
+        system_ids
+    }
+
+    /// Hash of the registered component, resource and system layout, including the schema
+    /// version and size of every serialized type. Snapshots only load into a registry with
+    /// the same schema hash
+    pub fn get_schema_hash(&self) -> u64 {
+        let mut hasher = SchemaHasher::default();
+        for (type_id, mask) in self.get_component_ids_by_mask() {
+            hasher.write(&mask.to_le_bytes());
+            match self.component_serializers.get(&type_id) {
+                Some(serializer) => serializer.write_schema(&mut hasher),
+                None => hasher.write(b"?"),
+            }
+        }
+        for (_, serializer) in self.get_resource_serializers_by_name() {
+            serializer.write_schema(&mut hasher);
+        }
+        for (type_id, name) in self.get_system_ids_by_name() {
+            hasher.write(name.as_bytes());
+            hasher.write(&self.system_masks.borrow()[&type_id].to_le_bytes());
+        }
+        hasher.finish()
+    }
+
+    pub fn snapshot(&self) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.write_snapshot(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Layout: header (magic, version, schema hash), allocation state, entity masks,
+    /// one column per component type with a value for every entity whose mask
+    /// contains it, serializable resources, the entities of every system and finally groups.
+    /// `buffer` is cleared first so it can be reused every frame.
+    pub fn write_snapshot(&self, buffer: &mut Vec<u8>) -> Result<()> {
+        buffer.clear();
+        buffer.extend_from_slice(SNAPSHOT_MAGIC);
+        write_u16(buffer, SNAPSHOT_VERSION);
+        write_u64(buffer, self.get_schema_hash());
+
+        write_u64(buffer, self.num_entities as u64);
+        write_entities(buffer, self.available_entity_spots.iter());
+        write_entities(buffer, self.entities_to_be_added.iter());
+        write_entities(buffer, self.entities_to_be_killed.iter());
+        for entity_mask in &self.entity_masks {
+            write_u32(buffer, *entity_mask);
+        }
+
+        for (type_id, component_mask) in self.get_component_ids_by_mask() {
+            let components = self.components.get(&type_id).ok_or_else(|| {
+                EcsErrors::ComponentDoesNotExist(self.get_component_label(type_id))
+            })?;
+            let serializer = self.component_serializers.get(&type_id);
+
+            for (entity_id, entity_mask) in self.entity_masks.iter().enumerate() {
+                if entity_mask & component_mask != component_mask {
+                    continue;
+                }
+                let serializer = serializer.ok_or_else(|| {
+                    EcsErrors::ComponentNotSerializable(self.get_component_label(type_id))
+                })?;
+                let component = components[entity_id]
+                    .as_ref()
+                    .ok_or_else(|| self.missing_component_error(Entity::new(entity_id), type_id))?;
+                (serializer.serialize)(&*component.borrow(), buffer)?;
+            }
+        }
+
+        for (type_id, serializer) in self.get_resource_serializers_by_name() {
+            match self.resources.get(&type_id) {
+                Some(resource) => {
+                    buffer.push(1);
+                    (serializer.serialize)(&*resource.borrow(), buffer)?;
+                }
+                None => buffer.push(0),
+            }
+        }
+
+        let system_entities = self.system_entities.borrow();
+        for (type_id, _) in self.get_system_ids_by_name() {
+            let entities = system_entities
+                .get(&type_id)
+                .ok_or(EcsErrors::SystemDoesNotExist)?;
+            write_entities(buffer, entities.iter());
+        }
+
+        write_u64(buffer, self.groups.len() as u64);
+        for (group, entities) in &self.groups {
+            write_string(buffer, group);
+            write_entities(buffer, entities.iter());
+        }
+
+        Ok(())
+    }
+
+    /// Nothing is changed unless the whole snapshot could be read
+    pub fn restore_snapshot(&mut self, mut bytes: &[u8]) -> Result<()> {
+        let bytes = &mut bytes;
+        read_magic(bytes)?;
+
+        let version = read_u16(bytes)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(EcsErrors::SnapshotVersionNotSupported(version).into());
+        }
+
+        let expected = self.get_schema_hash();
+        let found = read_u64(bytes)?;
+        if expected != found {
+            return Err(EcsErrors::SnapshotSchemaMismatch { expected, found }.into());
+        }
+
+        let num_entities = read_u64(bytes)? as usize;
+        let available_entity_spots = read_entities(bytes, num_entities)?;
+        let entities_to_be_added = read_entities(bytes, num_entities)?;
+        let entities_to_be_killed = read_entities(bytes, num_entities)?;
+
+        if num_entities > bytes.len() / 4 {
+            return Err(EcsErrors::SnapshotCorrupted("invalid entity count".to_string()).into());
+        }
+        let entity_masks = (0..num_entities)
+            .map(|_| read_u32(bytes))
+            .collect::<Result<Vec<u32>>>()?;
+
+        let mut components = HashMap::with_capacity(self.components.len());
+        for (type_id, component_mask) in self.get_component_ids_by_mask() {
+            let serializer = self.component_serializers.get(&type_id);
+            let mut components_vec = Vec::with_capacity(num_entities);
+
+            for entity_mask in &entity_masks {
+                if entity_mask & component_mask != component_mask {
+                    components_vec.push(None);
+                    continue;
+                }
+                let serializer = serializer.ok_or_else(|| {
+                    EcsErrors::ComponentNotSerializable(self.get_component_label(type_id))
+                })?;
+                components_vec.push(Some((serializer.deserialize)(bytes)?));
+            }
+
+            components.insert(type_id, components_vec);
+        }
+
+        let mut resources = Vec::new();
+        for (type_id, serializer) in self.get_resource_serializers_by_name() {
+            let resource = match read_u8(bytes)? {
+                0 => None,
+                1 => Some((serializer.deserialize)(bytes)?),
+                _ => {
+                    return Err(
+                        EcsErrors::SnapshotCorrupted("invalid resource flag".to_string()).into(),
+                    )
+                }
+            };
+            resources.push((type_id, resource));
+        }
+
+        let mut system_entities = HashMap::with_capacity(self.system_names.len());
+        for (type_id, _) in self.get_system_ids_by_name() {
+            let entities: BTreeSet<Entity> =
+                read_entities(bytes, num_entities)?.into_iter().collect();
+            system_entities.insert(type_id, entities);
+        }
+
+        let mut groups = BTreeMap::new();
+        for _ in 0..read_u64(bytes)? {
+            let group = read_string(bytes)?;
+            let entities: BTreeSet<Entity> =
+                read_entities(bytes, num_entities)?.into_iter().collect();
+            groups.insert(group, entities);
+        }
+
+        if !bytes.is_empty() {
+            return Err(EcsErrors::SnapshotCorrupted("trailing data".to_string()).into());
+        }
+
+        self.num_entities = num_entities;
+        self.available_entity_spots = available_entity_spots.into();
+        self.entities_to_be_added = entities_to_be_added;
+        self.entities_to_be_killed = entities_to_be_killed;
+        self.entity_masks = entity_masks;
+        self.components = components;
+        for (type_id, resource) in resources {
+            match resource {
+                Some(resource) => self.resources.insert(type_id, resource),
+                None => self.resources.remove(&type_id),
+            };
+        }
+        *self.system_entities.borrow_mut() = system_entities;
+        self.groups = groups;
+        self.rebuild_name_index();
+
+        Ok(())
+    }
+}
+
+fn clone_component<T: Any + Clone>(data: &dyn Any) -> Option<Component> {
+    let data = data.downcast_ref::<T>()?.clone();
+    Some(Rc::new(RefCell::new(data)))
 }
 
-/*
 #[cfg(test)]
 mod tests {
     use super::*;
-    #[derive(Debug)]
+    use crate::ecs::{
+        components::{RenderComponent, RenderLayer, TransformComponent},
+        reflect::FieldKind,
+    };
+    use sdl2::pixels::Color;
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     struct Health(i32);
-    #[derive(Debug)]
+    #[derive(Debug, Serialize, Deserialize)]
     struct Size(i32);
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Score(u32);
+
+    fn create_snapshot_registry(health_version: u32) -> Result<Registry> {
+        let mut registry = Registry::default();
+        registry.register_component::<Health>()?;
+        registry.register_component::<Size>()?;
+        registry.register_component_serializer_with_version::<Health>(health_version)?;
+        registry.register_component_serializer::<Size>()?;
+        registry.register_resource_serializer::<Score>();
+        Ok(registry)
+    }
+
+    #[test]
+    fn restoring_snapshots() -> Result<()> {
+        let mut registry = create_snapshot_registry(1)?;
+        let entity1 = registry.create_entity();
+        let entity2 = registry.create_entity();
+        registry.add_component(entity1, Health(50))?;
+        registry.add_component(entity2, Size(10))?;
+        registry.add_resource(Score(3));
+        registry.update()?;
+        let snapshot = registry.snapshot()?;
+
+        registry.get_component_mut::<Health>(entity1)?.0 = 0;
+        registry.kill_entity(entity2)?;
+        registry.create_entity();
+        registry.get_resource_mut::<Score>()?.0 = 7;
+        registry.update()?;
+
+        registry.restore_snapshot(&snapshot)?;
+        assert_eq!(registry.get_num_entities(), 2);
+        assert_eq!(registry.get_component::<Health>(entity1)?.0, 50);
+        assert_eq!(registry.get_component::<Size>(entity2)?.0, 10);
+        assert_eq!(registry.get_resource::<Score>()?.0, 3);
+        assert_eq!(registry.snapshot()?, snapshot);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejecting_snapshots_of_another_schema_version() -> Result<()> {
+        let mut registry = create_snapshot_registry(1)?;
+        let entity1 = registry.create_entity();
+        registry.add_component(entity1, Health(50))?;
+        let snapshot = registry.snapshot()?;
+
+        let mut other_registry = create_snapshot_registry(2)?;
+        assert_ne!(registry.get_schema_hash(), other_registry.get_schema_hash());
+        let err = other_registry.restore_snapshot(&snapshot).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::SnapshotSchemaMismatch { .. })
+        ));
+        assert_eq!(other_registry.get_num_entities(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejecting_snapshots_with_out_of_range_entities() -> Result<()> {
+        let mut registry = create_snapshot_registry(1)?;
+        registry.create_entity();
+        registry.create_entity();
+        let mut snapshot = registry.snapshot()?;
+
+        // Header, entity count, no available spots, then the second entity to be added
+        let offset = SNAPSHOT_MAGIC.len() + 2 + 8 + 8 + 8 + 8 + 8;
+        assert_eq!(snapshot[offset..offset + 8], 1u64.to_le_bytes());
+        snapshot[offset..offset + 8].copy_from_slice(&9u64.to_le_bytes());
+
+        let mut other_registry = create_snapshot_registry(1)?;
+        let err = other_registry.restore_snapshot(&snapshot).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::SnapshotCorrupted(_))
+        ));
+        assert_eq!(other_registry.get_num_entities(), 0);
+        other_registry.update()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn naming_components() -> Result<()> {
+        let mut registry = Registry::default();
+        registry.register_named_component::<Health>("hp")?;
+        registry.register_component::<Size>()?;
+        assert_eq!(
+            registry.get_component_type_id("hp"),
+            Some(TypeId::of::<Health>())
+        );
+        assert_eq!(registry.get_component_name::<Health>(), Some("hp"));
+        assert_eq!(registry.get_component_name::<Size>(), Some("Size"));
+        assert_eq!(registry.get_component_type_id("Health"), None);
+
+        let err = registry
+            .register_named_component::<Score>("hp")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::ComponentNameTaken(name)) if name == "hp"
+        ));
+
+        Ok(())
+    }
+
+    fn create_reflection_registry() -> Result<(Registry, Entity)> {
+        let mut registry = Registry::default();
+        registry.register_component::<TransformComponent>()?;
+        registry.register_component::<RenderComponent>()?;
+        registry.register_component::<Health>()?;
+        registry.register_component_reflection::<TransformComponent>()?;
+        registry.register_component_reflection::<RenderComponent>()?;
+        let entity = registry.create_entity();
+        registry.add_component(entity, TransformComponent { position: (1., 2.) })?;
+        registry.add_component(
+            entity,
+            RenderComponent {
+                width: 8,
+                height: 8,
+                color: Color::RGB(10, 20, 30),
+                layer: RenderLayer::World,
+                z_index: 0,
+            },
+        )?;
+        registry.add_component(entity, Health(50))?;
+        Ok((registry, entity))
+    }
+
+    #[test]
+    fn getting_and_setting_component_fields() -> Result<()> {
+        let (registry, entity) = create_reflection_registry()?;
+        assert_eq!(
+            registry.get_component_field(entity, "TransformComponent", "position.1")?,
+            ReflectValue::Float(2.)
+        );
+
+        // Ints are accepted for float fields
+        registry.set_component_field(
+            entity,
+            "TransformComponent",
+            "position.0",
+            ReflectValue::Int(5),
+        )?;
+        assert_eq!(
+            registry
+                .get_component::<TransformComponent>(entity)?
+                .position,
+            (5., 2.)
+        );
+
+        registry.set_component_field_from_str(entity, "RenderComponent", "color.g", "200")?;
+        registry.set_component_field_from_str(entity, "RenderComponent", "layer", "Ui")?;
+        let render = registry.get_component::<RenderComponent>(entity)?;
+        assert_eq!(render.color, Color::RGB(10, 200, 30));
+        assert_eq!(render.layer, RenderLayer::Ui);
+        drop(render);
+        assert_eq!(
+            registry.get_component_field(entity, "RenderComponent", "layer")?,
+            ReflectValue::String("Ui".to_string())
+        );
+        assert_eq!(
+            ReflectValue::parse(FieldKind::Bool, "is_active", "true")?,
+            ReflectValue::Bool(true)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejecting_invalid_component_fields() -> Result<()> {
+        let (registry, entity) = create_reflection_registry()?;
+        let err = registry
+            .get_component_field(entity, "TransformComponent", "position.2")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::FieldDoesNotExist { field, .. }) if field == "position.2"
+        ));
+        let err = registry
+            .set_component_field_from_str(entity, "RenderComponent", "colour.r", "1")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::FieldDoesNotExist { .. })
+        ));
+
+        let err = registry
+            .set_component_field(entity, "RenderComponent", "width", ReflectValue::Float(1.))
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::FieldTypeMismatch {
+                expected: FieldKind::Int,
+                ..
+            })
+        ));
+        let err = registry
+            .set_component_field_from_str(entity, "RenderComponent", "width", "wide")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::InvalidFieldValue { .. })
+        ));
+
+        // Out of range for the u8 color channel
+        for text in ["256", "-1"] {
+            let err = registry
+                .set_component_field_from_str(entity, "RenderComponent", "color.r", text)
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<EcsErrors>(),
+                Some(EcsErrors::InvalidFieldValue { value, .. }) if value == text
+            ));
+        }
+        let err = registry
+            .set_component_field_from_str(entity, "RenderComponent", "layer", "Sky")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::InvalidFieldValue { .. })
+        ));
+        let render = registry.get_component::<RenderComponent>(entity)?;
+        assert_eq!((render.width, render.color.r), (8, 10));
+        assert_eq!(render.layer, RenderLayer::World);
+        drop(render);
+
+        let err = registry
+            .get_component_field(entity, "Health", "0")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::ComponentNotReflectable(_))
+        ));
+
+        Ok(())
+    }
 
     #[test]
     fn getting_mut_components_from_entities() -> Result<()> {
@@ -370,7 +1734,125 @@ mod tests {
         }
         registry.remove_component::<Health>(entity1)?;
         let wrapped_entity1_health = registry.get_component::<Health>(entity1);
-        assert_eq!(wrapped_entity1_health.is_err(), true);
+        assert!(wrapped_entity1_health.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn adding_components_of_another_type_by_id() -> Result<()> {
+        let mut registry = Registry::default();
+        registry.register_component::<Health>()?;
+        registry.register_component::<Size>()?;
+        let entity1 = registry.create_entity();
+
+        let size: Component = Rc::new(RefCell::new(Size(10)));
+        let err = registry
+            .add_component_with_id(entity1, TypeId::of::<Health>(), size)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::ComponentTypeMismatch(_))
+        ));
+        assert_eq!(registry.get_entity_mask(entity1), Some(0));
+
+        let health: Component = Rc::new(RefCell::new(Health(50)));
+        registry.add_component_with_id(entity1, TypeId::of::<Health>(), health)?;
+        assert_eq!(registry.get_component::<Health>(entity1)?.0, 50);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cloning_entities_without_cloners() -> Result<()> {
+        let mut registry = Registry::default();
+        registry.register_component::<Health>()?;
+        registry.register_component::<Size>()?;
+        registry.register_component_cloner::<Health>()?;
+        let entity1 = registry.create_entity();
+        registry.add_component(entity1, Health(50))?;
+        registry.add_component(entity1, Size(10))?;
+
+        let err = registry.clone_entity(entity1).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::ComponentNotCloneable(_))
+        ));
+
+        registry.set_clone_policy(ClonePolicy::Skip);
+        let clone = registry.clone_entity(entity1)?;
+        assert_eq!(registry.get_component::<Health>(clone)?.0, 50);
+        assert!(!registry.entity_has_component::<Size>(clone));
+
+        Ok(())
+    }
+
+    #[test]
+    fn moving_killed_entities() -> Result<()> {
+        let mut registry = Registry::default();
+        let mut destination = Registry::default();
+        for registry in [&mut registry, &mut destination] {
+            registry.register_component::<Health>()?;
+            registry.register_component::<ParentComponent>()?;
+        }
+        let parent = registry.create_entity();
+        let child1 = registry.create_entity();
+        let child2 = registry.create_entity();
+        registry.add_component(parent, Health(50))?;
+        registry.add_component(child1, ParentComponent(parent))?;
+        registry.add_component(child2, ParentComponent(parent))?;
+        registry.update()?;
+
+        registry.kill_entity(child2)?;
+        let err = registry
+            .move_entities(&[child2], &mut destination)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::EntityIsKilled(2))
+        ));
+
+        let entity_map = registry.move_entities(&[parent], &mut destination)?;
+        assert_eq!(entity_map.len(), 2);
+        assert!(entity_map.contains_key(&child1));
+        assert!(!entity_map.contains_key(&child2));
+        assert_eq!(destination.get_num_entities(), 2);
+
+        registry.update()?;
+        assert_eq!(registry.get_entity_mask(child2), Some(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn registering_systems_after_entities() -> Result<()> {
+        struct HealthSystem;
+        let mut registry = Registry::default();
+        registry.register_component::<Health>()?;
+        registry.register_component::<Size>()?;
+        let entity1 = registry.create_entity();
+        let entity2 = registry.create_entity();
+        let entity3 = registry.create_entity();
+        registry.add_component(entity1, Health(50))?;
+        registry.add_component(entity2, Size(10))?;
+        registry.add_component(entity3, Health(100))?;
+        registry.update()?;
+        registry.kill_entity(entity3)?;
+        registry.update()?;
+        let entity4 = registry.create_entity();
+        registry.add_component(entity4, Health(20))?;
+
+        let health_mask = registry.get_component_mask::<Health>().unwrap();
+        registry.register_system::<HealthSystem>(health_mask)?;
+        let entities = registry.get_system_entities::<HealthSystem>()?;
+        assert_eq!(entities.into_iter().collect::<Vec<_>>(), vec![entity1]);
+
+        registry.update()?;
+        let entities = registry.get_system_entities::<HealthSystem>()?;
+        assert_eq!(
+            entities.into_iter().collect::<Vec<_>>(),
+            vec![entity1, entity4]
+        );
 
         Ok(())
     }
@@ -488,15 +1970,9 @@ mod tests {
 
         assert_eq!(entity1.0, 0);
         assert_eq!(entity2.0, 1);
-        assert_eq!(registry.entities_to_be_added.borrow().len(), 2);
-        assert_eq!(
-            registry.entities_to_be_added.borrow().contains(&entity1),
-            true
-        );
-        assert_eq!(
-            registry.entities_to_be_added.borrow().contains(&entity2),
-            true
-        );
+        assert_eq!(registry.entities_to_be_added.len(), 2);
+        assert!(registry.entities_to_be_added.contains(&entity1));
+        assert!(registry.entities_to_be_added.contains(&entity2));
         assert_eq!(registry.get_num_entities(), 2);
 
         // testing if components_vec is filled with default None values (nullptr)
@@ -504,7 +1980,7 @@ mod tests {
             let components_vec = registry.components.get(type_id).unwrap();
             assert_eq!(components_vec.len(), 2);
             for el in components_vec.iter() {
-                assert_eq!(el.is_none(), true);
+                assert!(el.is_none());
             }
         }
         Ok(())
@@ -580,8 +2056,7 @@ mod tests {
         registry.register_component::<Type32>()?;
 
         let type33 = registry.register_component::<Type33>();
-        assert_eq!(type33.is_err(), true);
+        assert!(type33.is_err());
         Ok(())
     }
 }
-*/
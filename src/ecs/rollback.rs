@@ -0,0 +1,84 @@
+use super::{ecs_errors::EcsErrors, registry::Registry};
+use anyhow::Result;
+use std::collections::VecDeque;
+
+/// Ring buffer of the last `capacity` registry snapshots, keyed by frame number.
+/// Snapshot buffers of dropped frames are reused so saving every frame does not allocate
+/// once the buffer is warmed up.
+pub struct RollbackBuffer {
+    capacity: usize,
+    frames: VecDeque<(u64, Vec<u8>)>,
+    spare_buffers: Vec<Vec<u8>>,
+}
+
+impl RollbackBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            frames: VecDeque::with_capacity(capacity),
+            spare_buffers: vec![],
+        }
+    }
+
+    /// Frames have to be saved in increasing order, saving an older frame drops the
+    /// frames after it
+    pub fn save(&mut self, frame: u64, registry: &Registry) -> Result<()> {
+        self.discard_from(frame);
+
+        let mut buffer = if self.frames.len() >= self.capacity {
+            self.frames.pop_front().map(|(_, buffer)| buffer)
+        } else {
+            self.spare_buffers.pop()
+        }
+        .unwrap_or_default();
+
+        registry.write_snapshot(&mut buffer)?;
+        self.frames.push_back((frame, buffer));
+        Ok(())
+    }
+
+    /// Restores the registry to the state saved at `frame`. Newer frames are dropped since
+    /// they are going to be simulated again.
+    pub fn restore(&mut self, frame: u64, registry: &mut Registry) -> Result<()> {
+        let (_, buffer) = self
+            .frames
+            .iter()
+            .find(|(saved_frame, _)| *saved_frame == frame)
+            .ok_or(EcsErrors::RollbackFrameNotAvailable(frame))?;
+
+        registry.restore_snapshot(buffer)?;
+        self.discard_from(frame + 1);
+        Ok(())
+    }
+
+    pub fn contains(&self, frame: u64) -> bool {
+        self.frames
+            .iter()
+            .any(|(saved_frame, _)| *saved_frame == frame)
+    }
+
+    pub fn get_oldest_frame(&self) -> Option<u64> {
+        self.frames.front().map(|(frame, _)| *frame)
+    }
+
+    pub fn get_newest_frame(&self) -> Option<u64> {
+        self.frames.back().map(|(frame, _)| *frame)
+    }
+
+    pub fn clear(&mut self) {
+        while let Some((_, buffer)) = self.frames.pop_back() {
+            self.spare_buffers.push(buffer);
+        }
+    }
+
+    fn discard_from(&mut self, frame: u64) {
+        while let Some((saved_frame, _)) = self.frames.back() {
+            if *saved_frame < frame {
+                break;
+            }
+            if let Some((_, buffer)) = self.frames.pop_back() {
+                self.spare_buffers.push(buffer);
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use super::{
+    ecs_errors::EcsErrors,
+    reflect::short_type_name,
+    registry::{Component, Entity},
+};
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{any::Any, cell::RefCell, rc::Rc};
+
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"ECSS";
+pub const SNAPSHOT_VERSION: u16 = 3;
+
+/// Type erased (de)serialization functions, captured when a component type is
+/// registered with `Registry::register_component_serializer`. The binary functions are
+/// used by snapshots, `from_json` by prefab and level files.
+#[derive(Clone, Copy)]
+pub struct ComponentSerializer {
+    pub type_name: &'static str,
+    /// Bumped by hand when the serialized fields change
+    pub schema_version: u32,
+    /// `size_of::<T>()`, catches most field changes without a version bump
+    pub layout_size: usize,
+    pub serialize: fn(&dyn Any, &mut Vec<u8>) -> Result<()>,
+    pub deserialize: fn(&mut &[u8]) -> Result<Component>,
+    pub from_json: fn(serde_json::Value) -> serde_json::Result<Component>,
+}
+
+impl ComponentSerializer {
+    pub fn of<T: Any + Serialize + DeserializeOwned>() -> Self {
+        Self::with_version::<T>(0)
+    }
+
+    pub fn with_version<T: Any + Serialize + DeserializeOwned>(schema_version: u32) -> Self {
+        Self {
+            type_name: std::any::type_name::<T>(),
+            schema_version,
+            layout_size: std::mem::size_of::<T>(),
+            serialize: serialize_component::<T>,
+            deserialize: deserialize_component::<T>,
+            from_json: component_from_json::<T>,
+        }
+    }
+
+    /// Everything a snapshot of the type depends on, for the schema hash
+    pub fn write_schema(&self, hasher: &mut SchemaHasher) {
+        hasher.write(self.type_name.as_bytes());
+        hasher.write(&self.schema_version.to_le_bytes());
+        hasher.write(&(self.layout_size as u64).to_le_bytes());
+    }
+}
+
+fn serialize_component<T: Any + Serialize>(data: &dyn Any, buffer: &mut Vec<u8>) -> Result<()> {
+    let data = data
+        .downcast_ref::<T>()
+        .ok_or_else(|| EcsErrors::ComponentDoesNotExist(short_type_name::<T>().to_string()))?;
+    bincode::serialize_into(buffer, data)?;
+    Ok(())
+}
+
+fn deserialize_component<T: Any + DeserializeOwned>(bytes: &mut &[u8]) -> Result<Component> {
+    let data: T = bincode::deserialize_from(bytes)
+        .map_err(|err| EcsErrors::SnapshotCorrupted(err.to_string()))?;
+    Ok(Rc::new(RefCell::new(data)))
+}
+
+fn component_from_json<T: Any + DeserializeOwned>(
+    value: serde_json::Value,
+) -> serde_json::Result<Component> {
+    let data: T = serde_json::from_value(value)?;
+    Ok(Rc::new(RefCell::new(data)))
+}
+
+/// FNV-1a, used for the snapshot schema hash because it is stable between builds
+/// unlike `DefaultHasher`.
+pub struct SchemaHasher(u64);
+
+impl Default for SchemaHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl SchemaHasher {
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub fn write_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+pub fn write_u64(buffer: &mut Vec<u8>, value: u64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn read_bytes<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if bytes.len() < len {
+        return Err(EcsErrors::SnapshotCorrupted("unexpected end of data".to_string()).into());
+    }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Ok(head)
+}
+
+pub fn read_magic(bytes: &mut &[u8]) -> Result<()> {
+    if read_bytes(bytes, SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC {
+        return Err(EcsErrors::SnapshotCorrupted("invalid header".to_string()).into());
+    }
+    Ok(())
+}
+
+pub fn read_u8(bytes: &mut &[u8]) -> Result<u8> {
+    Ok(read_bytes(bytes, 1)?[0])
+}
+
+pub fn read_u16(bytes: &mut &[u8]) -> Result<u16> {
+    let mut buf = [0; 2];
+    buf.copy_from_slice(read_bytes(bytes, 2)?);
+    Ok(u16::from_le_bytes(buf))
+}
+
+pub fn read_u32(bytes: &mut &[u8]) -> Result<u32> {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(read_bytes(bytes, 4)?);
+    Ok(u32::from_le_bytes(buf))
+}
+
+pub fn read_u64(bytes: &mut &[u8]) -> Result<u64> {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(read_bytes(bytes, 8)?);
+    Ok(u64::from_le_bytes(buf))
+}
+
+pub fn write_entities<'a>(
+    buffer: &mut Vec<u8>,
+    entities: impl ExactSizeIterator<Item = &'a Entity>,
+) {
+    write_u64(buffer, entities.len() as u64);
+    for entity in entities {
+        write_u64(buffer, entity.0 as u64);
+    }
+}
+
+/// NOTE: Ids are checked against `num_entities`, the registry indexes its masks with them
+pub fn read_entities(bytes: &mut &[u8], num_entities: usize) -> Result<Vec<Entity>> {
+    let len = read_u64(bytes)? as usize;
+    if len > bytes.len() / 8 {
+        return Err(EcsErrors::SnapshotCorrupted("invalid entity count".to_string()).into());
+    }
+    (0..len)
+        .map(|_| {
+            let id = read_u64(bytes)?;
+            if id >= num_entities as u64 {
+                return Err(
+                    EcsErrors::SnapshotCorrupted(format!("entity {} is out of range", id)).into(),
+                );
+            }
+            Ok(Entity::new(id as usize))
+        })
+        .collect()
+}
+
+pub fn write_string(buffer: &mut Vec<u8>, value: &str) {
+    write_u64(buffer, value.len() as u64);
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+pub fn read_string(bytes: &mut &[u8]) -> Result<String> {
+    let len = read_u64(bytes)? as usize;
+    let value = read_bytes(bytes, len)?;
+    String::from_utf8(value.to_vec())
+        .map_err(|_| EcsErrors::SnapshotCorrupted("invalid string".to_string()).into())
+}
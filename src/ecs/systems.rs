@@ -1,9 +1,14 @@
-use super::{ecs_errors::EcsErrors, registry::Registry};
+use super::{ecs_errors::EcsErrors, reflect::short_type_name, registry::Registry};
 use anyhow::Result;
 use std::any::Any;
 
+pub mod animation_system;
+pub mod camera_system;
+pub mod debug_draw_system;
 pub mod movement_system;
+pub mod particle_system;
 pub mod render_system;
+pub mod tilemap_system;
 
 // NOTE: comeback to this
 pub struct SystemMaskBuilder<'a> {
@@ -21,7 +26,7 @@ impl<'a> SystemMaskBuilder<'a> {
         let component_mask = self
             .registry
             .get_component_mask::<T>()
-            .ok_or(EcsErrors::ComponentDoesNotExist)?;
+            .ok_or_else(|| EcsErrors::ComponentDoesNotExist(short_type_name::<T>().to_string()))?;
         self.mask |= component_mask;
         Ok(self)
     }
@@ -0,0 +1,176 @@
+use crate::ecs::{
+    components::{AnimationComponent, AnimationMode, SpriteComponent},
+    registry::{Entity, Registry},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Fired when a clip with AnimationMode::Once reaches its end
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnimationFinishedEvent {
+    pub entity: Entity,
+    pub clip: String,
+}
+
+/// Resource holding the events of the last AnimationSystem update
+#[derive(Default, Serialize, Deserialize)]
+pub struct AnimationEvents(pub Vec<AnimationFinishedEvent>);
+
+pub struct AnimationSystem;
+
+impl AnimationSystem {
+    /// NOTE: Events only live for one update, they are cleared before the clips advance.
+    /// Entities whose current clip does not exist keep their sprite as it is.
+    pub fn update(registry: &mut Registry, dt: f64) -> Result<()> {
+        let mut events = vec![];
+        for entity in registry.get_system_entities::<AnimationSystem>()? {
+            let mut animation = registry.get_component_mut::<AnimationComponent>(entity)?;
+            let Some(clip) = animation.get_current_clip().cloned() else {
+                continue;
+            };
+
+            if !animation.is_finished {
+                animation.elapsed += dt;
+                let duration = clip.get_duration();
+                if clip.mode == AnimationMode::Once && animation.elapsed >= duration {
+                    animation.is_finished = true;
+                    events.push(AnimationFinishedEvent {
+                        entity,
+                        clip: animation.current_clip.clone(),
+                    });
+                } else if clip.mode != AnimationMode::Once && duration > 0. {
+                    // Keeps elapsed small so long running loops do not lose precision
+                    animation.elapsed %= duration;
+                }
+            }
+
+            let frame = clip.start_frame + clip.get_frame_at(animation.elapsed);
+            let mut sprite = registry.get_component_mut::<SpriteComponent>(entity)?;
+            sprite.src_rect = Some(animation.get_frame_rect(frame));
+        }
+
+        if !registry.has_resource::<AnimationEvents>() {
+            registry.add_resource(AnimationEvents::default());
+        }
+        registry.get_resource_mut::<AnimationEvents>()?.0 = events;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{
+        components::{AnimationClip, RenderLayer},
+        systems::SystemMaskBuilder,
+    };
+    use std::collections::BTreeMap;
+
+    /// A 4 column sprite sheet of 16x16 frames with a 3 frame `explode` clip
+    fn create_registry() -> Result<(Registry, Entity)> {
+        let mut registry = Registry::default();
+        registry.register_component::<AnimationComponent>()?;
+        registry.register_component::<SpriteComponent>()?;
+        let mask = SystemMaskBuilder::new(&registry)
+            .with::<AnimationComponent>()?
+            .with::<SpriteComponent>()?
+            .build();
+        registry.register_system::<AnimationSystem>(mask)?;
+
+        let entity = registry.create_entity();
+        let clip = AnimationClip {
+            start_frame: 2,
+            frame_count: 3,
+            frame_duration: 0.1,
+            mode: AnimationMode::Once,
+        };
+        registry.add_component(
+            entity,
+            AnimationComponent {
+                frame_width: 16,
+                frame_height: 16,
+                columns: 4,
+                clips: BTreeMap::from([("explode".to_string(), clip)]),
+                current_clip: "explode".to_string(),
+                elapsed: 0.,
+                is_finished: false,
+            },
+        )?;
+        registry.add_component(
+            entity,
+            SpriteComponent {
+                texture_id: "explosion".to_string(),
+                width: 16,
+                height: 16,
+                src_rect: None,
+                flip_horizontal: false,
+                flip_vertical: false,
+                layer: RenderLayer::Effects,
+                z_index: 0,
+            },
+        )?;
+        registry.update()?;
+        Ok((registry, entity))
+    }
+
+    fn get_events(registry: &Registry) -> Result<Vec<AnimationFinishedEvent>> {
+        Ok(registry.get_resource::<AnimationEvents>()?.0.clone())
+    }
+
+    #[test]
+    fn once_clips_finish_with_a_single_event() -> Result<()> {
+        let (mut registry, entity) = create_registry()?;
+        AnimationSystem::update(&mut registry, 0.15)?;
+        assert!(get_events(&registry)?.is_empty());
+        // Frame 3 of the sheet, the second frame of the clip
+        assert_eq!(
+            registry.get_component::<SpriteComponent>(entity)?.src_rect,
+            Some((48, 0, 16, 16))
+        );
+
+        AnimationSystem::update(&mut registry, 0.2)?;
+        assert_eq!(
+            get_events(&registry)?,
+            [AnimationFinishedEvent {
+                entity,
+                clip: "explode".to_string(),
+            }]
+        );
+        assert!(
+            registry
+                .get_component::<AnimationComponent>(entity)?
+                .is_finished
+        );
+
+        // The event is gone with the next update and the clip stays on its last frame
+        for _ in 0..3 {
+            AnimationSystem::update(&mut registry, 0.1)?;
+            assert!(get_events(&registry)?.is_empty());
+            assert_eq!(
+                registry.get_component::<SpriteComponent>(entity)?.src_rect,
+                Some((0, 16, 16, 16))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn playing_a_finished_clip_restarts_it() -> Result<()> {
+        let (mut registry, entity) = create_registry()?;
+        AnimationSystem::update(&mut registry, 0.5)?;
+        assert_eq!(get_events(&registry)?.len(), 1);
+
+        registry
+            .get_component_mut::<AnimationComponent>(entity)?
+            .play("explode");
+        AnimationSystem::update(&mut registry, 0.05)?;
+        assert!(get_events(&registry)?.is_empty());
+        assert_eq!(
+            registry.get_component::<SpriteComponent>(entity)?.src_rect,
+            Some((32, 0, 16, 16))
+        );
+        AnimationSystem::update(&mut registry, 0.5)?;
+        assert_eq!(get_events(&registry)?.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::ecs::{
+    components::{CameraComponent, TransformComponent},
+    registry::{Entity, Registry},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Resource with the size of the loaded level, levels without a size remove it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapSize {
+    /// In pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct CameraSystem;
+
+impl CameraSystem {
+    /// Moves cameras towards their target and keeps them inside their bounds, or the map
+    /// for cameras without bounds. A target without a TransformComponent (or that was
+    /// killed) leaves the camera where it is.
+    pub fn update(registry: &mut Registry, dt: f64) -> Result<()> {
+        let map_bounds = get_map_bounds(registry)?;
+        for entity in registry.get_system_entities::<CameraSystem>()? {
+            let mut camera = registry.get_component_mut::<CameraComponent>(entity)?;
+            let target = camera.target.or_else(|| {
+                camera
+                    .target_name
+                    .as_ref()
+                    .and_then(|name| registry.find_by_name(name))
+            });
+
+            if let Some(target_position) = target.and_then(|target| get_position(registry, target))
+            {
+                // Frame rate independent exponential smoothing
+                let amount = if camera.smoothing > 0. {
+                    1. - (-camera.smoothing * dt).exp()
+                } else {
+                    1.
+                };
+                camera.position.0 += (target_position.0 - camera.position.0) * amount;
+                camera.position.1 += (target_position.1 - camera.position.1) * amount;
+            }
+            if let Some(bounds) = camera.bounds.or(map_bounds) {
+                camera.clamp_to(bounds);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the camera RenderSystem draws through
+    pub fn get_active_camera(registry: &Registry) -> Result<Option<CameraComponent>> {
+        if !registry.has_system::<CameraSystem>() {
+            return Ok(None);
+        }
+        for entity in registry.get_system_entities::<CameraSystem>()? {
+            let camera = registry.get_component::<CameraComponent>(entity)?;
+            if camera.is_active {
+                return Ok(Some(camera.clone()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Without a camera world and screen coordinates are the same
+pub fn world_to_screen(camera: Option<&CameraComponent>, world: (f64, f64)) -> (i32, i32) {
+    let (x, y) = camera.map_or(world, |camera| camera.world_to_screen(world));
+    (x.round() as i32, y.round() as i32)
+}
+
+fn get_map_bounds(registry: &Registry) -> Result<Option<(f64, f64, f64, f64)>> {
+    if !registry.has_resource::<MapSize>() {
+        return Ok(None);
+    }
+    let map_size = registry.get_resource::<MapSize>()?;
+    Ok(Some((
+        0.,
+        0.,
+        map_size.width as f64,
+        map_size.height as f64,
+    )))
+}
+
+fn get_position(registry: &Registry, entity: Entity) -> Option<(f64, f64)> {
+    registry
+        .get_component::<TransformComponent>(entity)
+        .ok()
+        .map(|transform| transform.position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{
+        components::{NameComponent, MIN_CAMERA_ZOOM},
+        systems::SystemMaskBuilder,
+    };
+
+    fn create_registry() -> Result<Registry> {
+        let mut registry = Registry::default();
+        registry.register_component::<TransformComponent>()?;
+        registry.register_component::<CameraComponent>()?;
+        registry.register_component::<NameComponent>()?;
+        let mask = SystemMaskBuilder::new(&registry)
+            .with::<CameraComponent>()?
+            .build();
+        registry.register_system::<CameraSystem>(mask)?;
+        Ok(registry)
+    }
+
+    fn add_camera(registry: &mut Registry, camera: CameraComponent) -> Result<Entity> {
+        let entity = registry.create_entity();
+        registry.add_component(entity, camera)?;
+        registry.update()?;
+        Ok(entity)
+    }
+
+    fn add_target(registry: &mut Registry, position: (f64, f64)) -> Result<Entity> {
+        let entity = registry.create_entity();
+        registry.add_component(entity, TransformComponent { position })?;
+        Ok(entity)
+    }
+
+    fn get_camera_position(registry: &Registry, camera: Entity) -> Result<(f64, f64)> {
+        Ok(registry.get_component::<CameraComponent>(camera)?.position)
+    }
+
+    #[test]
+    fn cameras_follow_their_target() -> Result<()> {
+        let mut registry = create_registry()?;
+        let target = add_target(&mut registry, (100., 40.))?;
+        let camera = add_camera(
+            &mut registry,
+            CameraComponent {
+                position: (0., 0.),
+                target: Some(target),
+                ..CameraComponent::new((0, 0, 10, 10))
+            },
+        )?;
+        CameraSystem::update(&mut registry, 0.1)?;
+        assert_eq!(get_camera_position(&registry, camera)?, (100., 40.));
+
+        // Half of the way every second
+        {
+            let mut camera = registry.get_component_mut::<CameraComponent>(camera)?;
+            camera.position = (0., 0.);
+            camera.smoothing = 2f64.ln();
+        }
+        CameraSystem::update(&mut registry, 1.)?;
+        let (x, y) = get_camera_position(&registry, camera)?;
+        assert!((x - 50.).abs() < 1e-9 && (y - 20.).abs() < 1e-9);
+
+        // A killed target leaves the camera where it is
+        registry.kill_entity(target)?;
+        registry.update()?;
+        CameraSystem::update(&mut registry, 1.)?;
+        assert_eq!(get_camera_position(&registry, camera)?, (x, y));
+        Ok(())
+    }
+
+    #[test]
+    fn cameras_follow_targets_by_name() -> Result<()> {
+        let mut registry = create_registry()?;
+        let camera = add_camera(
+            &mut registry,
+            CameraComponent {
+                target_name: Some("player".to_string()),
+                ..CameraComponent::new((0, 0, 10, 10))
+            },
+        )?;
+        let start = get_camera_position(&registry, camera)?;
+        CameraSystem::update(&mut registry, 0.1)?;
+        assert_eq!(get_camera_position(&registry, camera)?, start);
+
+        let player = add_target(&mut registry, (-30., 12.))?;
+        registry.set_entity_name(player, "player")?;
+        CameraSystem::update(&mut registry, 0.1)?;
+        assert_eq!(get_camera_position(&registry, camera)?, (-30., 12.));
+        Ok(())
+    }
+
+    #[test]
+    fn cameras_are_kept_inside_their_bounds() -> Result<()> {
+        let mut registry = create_registry()?;
+        let camera = add_camera(
+            &mut registry,
+            CameraComponent {
+                position: (-50., 200.),
+                bounds: Some((0., 0., 100., 100.)),
+                ..CameraComponent::new((0, 0, 10, 10))
+            },
+        )?;
+        CameraSystem::update(&mut registry, 0.1)?;
+        assert_eq!(get_camera_position(&registry, camera)?, (5., 95.));
+
+        // A view bigger than the bounds is centered on them
+        {
+            let mut camera = registry.get_component_mut::<CameraComponent>(camera)?;
+            camera.bounds = Some((0., 0., 100., 50.));
+            camera.zoom = 0.05;
+        }
+        CameraSystem::update(&mut registry, 0.1)?;
+        assert_eq!(get_camera_position(&registry, camera)?, (50., 25.));
+        Ok(())
+    }
+
+    #[test]
+    fn cameras_without_bounds_are_kept_inside_the_map() -> Result<()> {
+        let mut registry = create_registry()?;
+        let camera = add_camera(
+            &mut registry,
+            CameraComponent {
+                position: (500., -20.),
+                ..CameraComponent::new((0, 0, 40, 20))
+            },
+        )?;
+        CameraSystem::update(&mut registry, 0.1)?;
+        assert_eq!(get_camera_position(&registry, camera)?, (500., -20.));
+
+        registry.add_resource(MapSize {
+            width: 200,
+            height: 100,
+        });
+        CameraSystem::update(&mut registry, 0.1)?;
+        assert_eq!(get_camera_position(&registry, camera)?, (180., 10.));
+        Ok(())
+    }
+
+    #[test]
+    fn world_and_screen_positions_convert_both_ways() {
+        let camera = CameraComponent {
+            position: (42., 34.),
+            zoom: 2.,
+            ..CameraComponent::new((10, 20, 64, 48))
+        };
+        assert_eq!(camera.get_view_rect(), (26., 22., 32., 24.));
+        assert_eq!(camera.world_to_screen((26., 22.)), (10., 20.));
+        assert_eq!(camera.world_to_screen((42., 34.)), (42., 44.));
+        for world in [(0., 0.), (26.5, 40.25), (-13., 7.)] {
+            assert_eq!(camera.screen_to_world(camera.world_to_screen(world)), world);
+        }
+        assert_eq!(world_to_screen(Some(&camera), (30.2, 22.)), (18, 20));
+        assert_eq!(world_to_screen(None, (30.2, 22.)), (30, 22));
+    }
+
+    #[test]
+    fn zero_zoom_is_clamped() {
+        let camera = CameraComponent {
+            zoom: 0.,
+            ..CameraComponent::new((0, 0, 64, 48))
+        };
+        assert_eq!(camera.get_zoom(), MIN_CAMERA_ZOOM);
+        let (width, height) = camera.get_view_size();
+        assert!(width.is_finite() && height.is_finite());
+        let (x, y) = camera.screen_to_world((10., 10.));
+        assert!(x.is_finite() && y.is_finite());
+    }
+}
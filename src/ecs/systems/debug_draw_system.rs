@@ -0,0 +1,257 @@
+use crate::{
+    ecs::{
+        components::{
+            BoxColliderComponent, CameraComponent, NameComponent, TransformComponent,
+            VelocityComponent,
+        },
+        registry::{Entity, Registry},
+        systems::camera_system::{world_to_screen, CameraSystem},
+    },
+    renderer::Renderer,
+};
+use anyhow::Result;
+use sdl2::{pixels::Color, rect::Rect};
+
+pub const COLLIDER_COLOR: Color = Color::RGB(0, 255, 0);
+pub const VELOCITY_COLOR: Color = Color::RGB(255, 255, 0);
+pub const LABEL_COLOR: Color = Color::RGB(255, 255, 255);
+/// Labels are drawn this far above the entity position
+pub const LABEL_OFFSET: i32 = 16;
+
+/// What DebugDrawSystem draws, every category is off by default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugDrawSettings {
+    pub colliders: bool,
+    pub velocities: bool,
+    pub labels: bool,
+    /// Velocity lines are as long as the distance moved in this many seconds
+    pub velocity_scale: f64,
+}
+
+impl Default for DebugDrawSettings {
+    fn default() -> Self {
+        Self {
+            colliders: false,
+            velocities: false,
+            labels: false,
+            velocity_scale: 1.,
+        }
+    }
+}
+
+impl DebugDrawSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.colliders || self.velocities || self.labels
+    }
+}
+
+pub struct DebugDrawSystem;
+
+impl DebugDrawSystem {
+    /// Draws on top of the world through the active camera, call it after RenderSystem
+    pub fn update(
+        registry: &Registry,
+        settings: &DebugDrawSettings,
+        renderer: &mut dyn Renderer,
+    ) -> Result<()> {
+        if !settings.is_enabled() {
+            return Ok(());
+        }
+
+        let camera = CameraSystem::get_active_camera(registry)?;
+        let zoom = camera.as_ref().map_or(1., CameraComponent::get_zoom);
+        for entity in registry.get_system_entities::<DebugDrawSystem>()? {
+            let position = registry
+                .get_component::<TransformComponent>(entity)?
+                .position;
+            let screen_position = world_to_screen(camera.as_ref(), position);
+
+            if settings.colliders && registry.entity_has_component::<BoxColliderComponent>(entity) {
+                let collider = registry.get_component::<BoxColliderComponent>(entity)?;
+                let (x, y) = world_to_screen(
+                    camera.as_ref(),
+                    (
+                        position.0 + collider.offset.0,
+                        position.1 + collider.offset.1,
+                    ),
+                );
+                let rect = Rect::new(
+                    x,
+                    y,
+                    (collider.width as f64 * zoom).round() as u32,
+                    (collider.height as f64 * zoom).round() as u32,
+                );
+                renderer.draw_rect(rect, COLLIDER_COLOR)?;
+            }
+
+            if settings.velocities && registry.entity_has_component::<VelocityComponent>(entity) {
+                let velocity = registry.get_component::<VelocityComponent>(entity)?;
+                let end = world_to_screen(
+                    camera.as_ref(),
+                    (
+                        position.0 + velocity.0 * settings.velocity_scale,
+                        position.1 + velocity.1 * settings.velocity_scale,
+                    ),
+                );
+                renderer.draw_line(screen_position, end, VELOCITY_COLOR)?;
+            }
+
+            if settings.labels {
+                let label = get_label(registry, entity)?;
+                let position = (screen_position.0, screen_position.1 - LABEL_OFFSET);
+                renderer.draw_text(&label, position, LABEL_COLOR)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The entity name, or its id for unnamed entities
+fn get_label(registry: &Registry, entity: Entity) -> Result<String> {
+    if registry.entity_has_component::<NameComponent>(entity) {
+        let name = registry.get_component::<NameComponent>(entity)?;
+        return Ok(name.0.clone());
+    }
+    Ok(format!("#{}", entity.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::systems::SystemMaskBuilder,
+        renderer::recording_renderer::{DrawCall, RecordingRenderer},
+    };
+
+    /// A named entity with a collider and a velocity, an unnamed one with neither, and a
+    /// 64x48 camera at 2x zoom looking at the world area (26, 22, 32, 24)
+    fn create_registry() -> Result<Registry> {
+        let mut registry = Registry::default();
+        registry.register_component::<TransformComponent>()?;
+        registry.register_component::<BoxColliderComponent>()?;
+        registry.register_component::<VelocityComponent>()?;
+        registry.register_component::<NameComponent>()?;
+        registry.register_component::<CameraComponent>()?;
+        let debug_draw_mask = SystemMaskBuilder::new(&registry)
+            .with::<TransformComponent>()?
+            .build();
+        registry.register_system::<DebugDrawSystem>(debug_draw_mask)?;
+        let camera_mask = SystemMaskBuilder::new(&registry)
+            .with::<CameraComponent>()?
+            .build();
+        registry.register_system::<CameraSystem>(camera_mask)?;
+
+        let player = registry.create_entity();
+        registry.add_component(
+            player,
+            TransformComponent {
+                position: (30., 25.),
+            },
+        )?;
+        registry.add_component(
+            player,
+            BoxColliderComponent {
+                width: 4,
+                height: 6,
+                offset: (1., -1.),
+            },
+        )?;
+        registry.add_component(player, VelocityComponent(2., 3.))?;
+        registry.set_entity_name(player, "player")?;
+
+        let rock = registry.create_entity();
+        registry.add_component(
+            rock,
+            TransformComponent {
+                position: (40., 30.),
+            },
+        )?;
+
+        let camera = registry.create_entity();
+        registry.add_component(
+            camera,
+            CameraComponent {
+                position: (42., 34.),
+                zoom: 2.,
+                ..CameraComponent::new((0, 0, 64, 48))
+            },
+        )?;
+        registry.update()?;
+        Ok(registry)
+    }
+
+    fn draw(registry: &Registry, settings: DebugDrawSettings) -> Result<Vec<DrawCall>> {
+        let mut renderer = RecordingRenderer::new();
+        DebugDrawSystem::update(registry, &settings, &mut renderer)?;
+        Ok(renderer.take_draw_calls())
+    }
+
+    #[test]
+    fn nothing_is_drawn_by_default() -> Result<()> {
+        let registry = create_registry()?;
+        assert!(draw(&registry, DebugDrawSettings::default())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn colliders_are_drawn_through_the_camera() -> Result<()> {
+        let registry = create_registry()?;
+        let settings = DebugDrawSettings {
+            colliders: true,
+            ..DebugDrawSettings::default()
+        };
+        assert_eq!(
+            draw(&registry, settings)?,
+            [DrawCall::DrawRect {
+                rect: Rect::new(10, 4, 8, 12),
+                color: COLLIDER_COLOR,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn velocities_are_drawn_through_the_camera() -> Result<()> {
+        let registry = create_registry()?;
+        let settings = DebugDrawSettings {
+            velocities: true,
+            velocity_scale: 0.5,
+            ..DebugDrawSettings::default()
+        };
+        // From (30, 25) to (31, 26.5) in the world
+        assert_eq!(
+            draw(&registry, settings)?,
+            [DrawCall::DrawLine {
+                start: (8, 6),
+                end: (10, 9),
+                color: VELOCITY_COLOR,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unnamed_entities_are_labelled_with_their_id() -> Result<()> {
+        let registry = create_registry()?;
+        let settings = DebugDrawSettings {
+            labels: true,
+            ..DebugDrawSettings::default()
+        };
+        assert_eq!(
+            draw(&registry, settings)?,
+            [
+                DrawCall::DrawText {
+                    text: "player".to_string(),
+                    position: (8, 6 - LABEL_OFFSET),
+                    color: LABEL_COLOR,
+                },
+                DrawCall::DrawText {
+                    text: "#1".to_string(),
+                    position: (28, 16 - LABEL_OFFSET),
+                    color: LABEL_COLOR,
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,16 +1,19 @@
-use crate::ecs::{components::TransformComponent, registry::Registry};
+use crate::ecs::{
+    components::{TransformComponent, VelocityComponent},
+    registry::Registry,
+};
 use anyhow::Result;
 
-use super::SystemMaskBuilder;
-
 pub struct MovementSystem;
 
 impl MovementSystem {
-    pub fn update(register: &mut Registry, dt: f64) -> Result<()> {
-        //for entity in register.get_system_entities::<MovementSystem>()? {
-            //let mut tf = register.get_component_mut::<TransformComponent>(*entity)?;
-            //tf.position.0 += 20. * dt;
-        //}
+    pub fn update(registry: &mut Registry, dt: f64) -> Result<()> {
+        for entity in registry.get_system_entities::<MovementSystem>()? {
+            let velocity = registry.get_component::<VelocityComponent>(entity)?;
+            let mut transform = registry.get_component_mut::<TransformComponent>(entity)?;
+            transform.position.0 += velocity.0 * dt;
+            transform.position.1 += velocity.1 * dt;
+        }
         Ok(())
     }
 
@@ -0,0 +1,220 @@
+use crate::ecs::{
+    components::{Particle, ParticleEmitterComponent, TransformComponent},
+    registry::Registry,
+};
+use anyhow::Result;
+
+pub struct ParticleSystem;
+
+impl ParticleSystem {
+    /// Ages and moves the particles of every emitter, drops the dead ones and emits new
+    /// ones at the emitter position.
+    /// NOTE: The random number generator starts from `seed` on the first update, the burst
+    /// is emitted then too.
+    pub fn update(registry: &mut Registry, dt: f64) -> Result<()> {
+        for entity in registry.get_system_entities::<ParticleSystem>()? {
+            let origin = registry
+                .get_component::<TransformComponent>(entity)?
+                .position;
+            let mut emitter = registry.get_component_mut::<ParticleEmitterComponent>(entity)?;
+            if !emitter.is_started {
+                emitter.is_started = true;
+                emitter.rng_state = emitter.seed;
+                let burst = emitter.burst;
+                emitter.emit_burst(burst);
+            }
+
+            // Dead particles are swapped with the last one so the pool stays compact
+            let gravity = emitter.gravity;
+            let mut index = 0;
+            while index < emitter.particles.len() {
+                let particle = &mut emitter.particles[index];
+                particle.age += dt;
+                if particle.age >= particle.lifetime {
+                    emitter.particles.swap_remove(index);
+                    continue;
+                }
+                particle.velocity.0 += gravity.0 * dt;
+                particle.velocity.1 += gravity.1 * dt;
+                particle.position.0 += particle.velocity.0 * dt;
+                particle.position.1 += particle.velocity.1 * dt;
+                index += 1;
+            }
+
+            let mut count = emitter.pending_burst as usize;
+            emitter.pending_burst = 0;
+            if emitter.is_emitting {
+                emitter.emit_accumulator += emitter.rate * dt;
+                let emitted = emitter.emit_accumulator.floor();
+                emitter.emit_accumulator -= emitted;
+                count += emitted as usize;
+            }
+            for _ in 0..count {
+                if emitter.particles.len() >= emitter.max_particles {
+                    break;
+                }
+                let particle = Self::create_particle(&mut emitter, origin);
+                emitter.particles.push(particle);
+            }
+        }
+        Ok(())
+    }
+
+    fn create_particle(emitter: &mut ParticleEmitterComponent, origin: (f64, f64)) -> Particle {
+        let lifetime = lerp(emitter.lifetime, next_random(&mut emitter.rng_state));
+        let angle = (emitter.direction
+            + (next_random(&mut emitter.rng_state) - 0.5) * emitter.spread)
+            .to_radians();
+        let speed = lerp(emitter.speed, next_random(&mut emitter.rng_state));
+        Particle {
+            position: origin,
+            velocity: (angle.cos() * speed, angle.sin() * speed),
+            age: 0.,
+            lifetime,
+        }
+    }
+}
+
+fn lerp(range: (f64, f64), t: f64) -> f64 {
+    range.0 + (range.1 - range.0) * t
+}
+
+/// SplitMix64, returns a number in [0, 1)
+fn next_random(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{registry::Entity, systems::SystemMaskBuilder};
+    use serde_json::json;
+
+    fn create_emitter(seed: u64) -> Result<ParticleEmitterComponent> {
+        Ok(serde_json::from_value(json!({
+            "rate": 20.,
+            "burst": 5,
+            "lifetime": [0.5, 1.5],
+            "direction": -90.,
+            "spread": 60.,
+            "speed": [20., 40.],
+            "gravity": [0., 30.],
+            "start_color": [255, 200, 0, 255],
+            "end_color": [80, 80, 80, 0],
+            "start_size": 4.,
+            "end_size": 1.,
+            "seed": seed,
+        }))?)
+    }
+
+    fn create_registry(emitter: ParticleEmitterComponent) -> Result<(Registry, Entity)> {
+        let mut registry = Registry::default();
+        registry.register_component::<TransformComponent>()?;
+        registry.register_component::<ParticleEmitterComponent>()?;
+        let mask = SystemMaskBuilder::new(&registry)
+            .with::<TransformComponent>()?
+            .with::<ParticleEmitterComponent>()?
+            .build();
+        registry.register_system::<ParticleSystem>(mask)?;
+        let entity = registry.create_entity();
+        registry.add_component(
+            entity,
+            TransformComponent {
+                position: (100., 50.),
+            },
+        )?;
+        registry.add_component(entity, emitter)?;
+        registry.update()?;
+        Ok((registry, entity))
+    }
+
+    fn simulate(seed: u64, updates: usize) -> Result<Vec<Particle>> {
+        let (mut registry, entity) = create_registry(create_emitter(seed)?)?;
+        for _ in 0..updates {
+            ParticleSystem::update(&mut registry, 0.1)?;
+        }
+        let particles = registry
+            .get_component::<ParticleEmitterComponent>(entity)?
+            .particles
+            .clone();
+        Ok(particles)
+    }
+
+    #[test]
+    fn emission_only_depends_on_the_seed() -> Result<()> {
+        let particles = simulate(7, 12)?;
+        assert!(!particles.is_empty());
+        assert_eq!(simulate(7, 12)?, particles);
+        assert_ne!(simulate(8, 12)?, particles);
+        Ok(())
+    }
+
+    #[test]
+    fn bursts_are_capped_by_max_particles_and_expire() -> Result<()> {
+        let mut emitter = create_emitter(1)?;
+        emitter.rate = 0.;
+        emitter.burst = 20;
+        emitter.max_particles = 16;
+        emitter.lifetime = (0.5, 0.5);
+        let (mut registry, entity) = create_registry(emitter)?;
+
+        ParticleSystem::update(&mut registry, 0.1)?;
+        {
+            let emitter = registry.get_component::<ParticleEmitterComponent>(entity)?;
+            assert_eq!(emitter.particles.len(), 16);
+            assert!(!emitter.is_finished());
+        }
+
+        ParticleSystem::update(&mut registry, 0.5)?;
+        let emitter = registry.get_component::<ParticleEmitterComponent>(entity)?;
+        assert!(emitter.particles.is_empty());
+        assert!(emitter.is_finished());
+        Ok(())
+    }
+
+    #[test]
+    fn particles_move_with_their_velocity_and_gravity() -> Result<()> {
+        let mut emitter = create_emitter(1)?;
+        emitter.rate = 3.;
+        emitter.burst = 0;
+        emitter.direction = 0.;
+        emitter.spread = 0.;
+        emitter.speed = (10., 10.);
+        emitter.gravity = (0., 10.);
+        emitter.lifetime = (10., 10.);
+        let (mut registry, entity) = create_registry(emitter)?;
+
+        // 1.5 particles are due, the half particle is carried over to the next update
+        ParticleSystem::update(&mut registry, 0.5)?;
+        assert_eq!(
+            registry
+                .get_component::<ParticleEmitterComponent>(entity)?
+                .particles,
+            [Particle {
+                position: (100., 50.),
+                velocity: (10., 0.),
+                age: 0.,
+                lifetime: 10.,
+            }]
+        );
+
+        ParticleSystem::update(&mut registry, 0.5)?;
+        let emitter = registry.get_component::<ParticleEmitterComponent>(entity)?;
+        assert_eq!(emitter.particles.len(), 3);
+        assert_eq!(
+            emitter.particles[0],
+            Particle {
+                position: (105., 52.5),
+                velocity: (10., 5.),
+                age: 0.5,
+                lifetime: 10.,
+            }
+        );
+        Ok(())
+    }
+}
@@ -1,17 +1,648 @@
-use crate::ecs::{components::TransformComponent, registry::Registry};
+use crate::{
+    ecs::{
+        components::{
+            get_tile_gid, CameraComponent, ParticleEmitterComponent, RenderComponent, RenderLayer,
+            Shape, ShapeComponent, SpriteComponent, TilemapComponent, TransformComponent,
+            FLIPPED_HORIZONTALLY_FLAG, FLIPPED_VERTICALLY_FLAG, TILEMAP_CHUNK_SIZE,
+        },
+        registry::{Entity, Registry},
+        systems::camera_system::CameraSystem,
+    },
+    renderer::{shapes, Renderer, TextureFlip},
+};
 use anyhow::Result;
+use sdl2::rect::Rect;
+
+/// Tilemaps, then rectangles, then sprites, then shapes, then particles when an entity has
+/// several on the same layer and z_index
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Drawable {
+    Tilemap,
+    Rect,
+    Sprite,
+    Shape,
+    Particles,
+}
+
+/// Resource with the draw statistics of the last RenderSystem update
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    pub drawn: usize,
+    /// Skipped because they were fully outside of the camera viewport
+    pub culled: usize,
+    pub chunks_drawn: usize,
+    pub chunks_culled: usize,
+}
 
 pub struct RenderSystem;
 
 impl RenderSystem {
-    pub fn update(register: &mut Registry, dt: f64) -> Result<()> {
-        for entity in register.get_system_entities::<RenderSystem>()? {
-            //let mut tf = register.get_component_mut::<TransformComponent>(*entity)?;
+    /// Draws every entity with a TransformComponent and a RenderComponent (filled rectangle),
+    /// a SpriteComponent (texture), a ShapeComponent, a TilemapComponent or a
+    /// ParticleEmitterComponent, sorted by layer, z_index and then entity id.
+    /// Positions are world coordinates seen through the active camera, except on the Ui
+    /// layer which is always drawn in screen coordinates.
+    /// Drawables fully outside of the camera viewport are culled, the counts are stored in
+    /// the RenderStats resource when the registry has one.
+    pub fn update(registry: &Registry, renderer: &mut dyn Renderer) -> Result<()> {
+        let mut stats = RenderStats::default();
+        let camera = CameraSystem::get_active_camera(registry)?;
+        for (layer, _, entity, drawable) in Self::get_draw_order(registry)? {
+            let position = registry
+                .get_component::<TransformComponent>(entity)?
+                .position;
+            let camera = camera.as_ref().filter(|_| layer != RenderLayer::Ui);
+            let dst = match drawable {
+                Drawable::Tilemap => {
+                    let tilemap = registry.get_component::<TilemapComponent>(entity)?;
+                    to_screen_rect(
+                        camera,
+                        position,
+                        (
+                            tilemap.width * tilemap.tile_width,
+                            tilemap.height * tilemap.tile_height,
+                        ),
+                    )
+                }
+                Drawable::Rect => {
+                    let render = registry.get_component::<RenderComponent>(entity)?;
+                    to_screen_rect(camera, position, (render.width, render.height))
+                }
+                Drawable::Sprite => {
+                    let sprite = registry.get_component::<SpriteComponent>(entity)?;
+                    to_screen_rect(camera, position, (sprite.width, sprite.height))
+                }
+                Drawable::Shape => {
+                    let shape = registry.get_component::<ShapeComponent>(entity)?;
+                    // Padded by the stroke width since lines are centered on their points
+                    let padding = shape.stroke_width as f64;
+                    let (x, y, width, height) = shape.get_bounds();
+                    to_screen_rect(
+                        camera,
+                        (position.0 + x - padding, position.1 + y - padding),
+                        (
+                            (width + 2. * padding).ceil() as u32,
+                            (height + 2. * padding).ceil() as u32,
+                        ),
+                    )
+                }
+                Drawable::Particles => {
+                    let emitter = registry.get_component::<ParticleEmitterComponent>(entity)?;
+                    // Particles are in world coordinates, not relative to the emitter
+                    let (x, y, width, height) = emitter.get_bounds().unwrap_or_default();
+                    to_screen_rect(camera, (x, y), (width.ceil() as u32, height.ceil() as u32))
+                }
+            };
+
+            if camera.is_some_and(|camera| !is_in_viewport(camera, dst)) {
+                stats.culled += 1;
+                continue;
+            }
+            stats.drawn += 1;
+
+            match drawable {
+                Drawable::Tilemap => {
+                    let tilemap = registry.get_component::<TilemapComponent>(entity)?;
+                    draw_tilemap(&tilemap, camera, position, renderer, &mut stats)?;
+                }
+                Drawable::Rect => {
+                    let render = registry.get_component::<RenderComponent>(entity)?;
+                    renderer.fill_rect(dst, render.color)?;
+                }
+                Drawable::Sprite => {
+                    let sprite = registry.get_component::<SpriteComponent>(entity)?;
+                    let src = sprite
+                        .src_rect
+                        .map(|(x, y, width, height)| Rect::new(x, y, width, height));
+                    let flip = TextureFlip {
+                        horizontal: sprite.flip_horizontal,
+                        vertical: sprite.flip_vertical,
+                    };
+                    renderer.draw_texture(&sprite.texture_id, src, dst, flip)?;
+                }
+                Drawable::Shape => {
+                    let shape = registry.get_component::<ShapeComponent>(entity)?;
+                    draw_shape(&shape, camera, position, renderer)?;
+                }
+                Drawable::Particles => {
+                    let emitter = registry.get_component::<ParticleEmitterComponent>(entity)?;
+                    draw_particles(&emitter, camera, renderer)?;
+                }
+            }
+        }
+
+        if registry.has_resource::<RenderStats>() {
+            *registry.get_resource_mut::<RenderStats>()? = stats;
         }
         Ok(())
     }
 
+    /// NOTE: Every key is unique, so the order does not depend on the iteration order
+    fn get_draw_order(registry: &Registry) -> Result<Vec<(RenderLayer, i32, Entity, Drawable)>> {
+        let mut draw_order = vec![];
+        for entity in registry.get_system_entities::<RenderSystem>()? {
+            if registry.entity_has_component::<TilemapComponent>(entity) {
+                let tilemap = registry.get_component::<TilemapComponent>(entity)?;
+                draw_order.push((tilemap.layer, tilemap.z_index, entity, Drawable::Tilemap));
+            }
+            if registry.entity_has_component::<RenderComponent>(entity) {
+                let render = registry.get_component::<RenderComponent>(entity)?;
+                draw_order.push((render.layer, render.z_index, entity, Drawable::Rect));
+            }
+            if registry.entity_has_component::<SpriteComponent>(entity) {
+                let sprite = registry.get_component::<SpriteComponent>(entity)?;
+                draw_order.push((sprite.layer, sprite.z_index, entity, Drawable::Sprite));
+            }
+            if registry.entity_has_component::<ShapeComponent>(entity) {
+                let shape = registry.get_component::<ShapeComponent>(entity)?;
+                draw_order.push((shape.layer, shape.z_index, entity, Drawable::Shape));
+            }
+            // Emitters without particles have nothing to draw or cull
+            if registry.entity_has_component::<ParticleEmitterComponent>(entity) {
+                let emitter = registry.get_component::<ParticleEmitterComponent>(entity)?;
+                if !emitter.particles.is_empty() {
+                    draw_order.push((emitter.layer, emitter.z_index, entity, Drawable::Particles));
+                }
+            }
+        }
+        draw_order.sort();
+        Ok(draw_order)
+    }
+
     pub fn get_system_mask() -> u32 {
         0
     }
 }
+
+fn to_screen_rect(
+    camera: Option<&CameraComponent>,
+    position: (f64, f64),
+    size: (u32, u32),
+) -> Rect {
+    let Some(camera) = camera else {
+        return Rect::new(
+            position.0.round() as i32,
+            position.1.round() as i32,
+            size.0,
+            size.1,
+        );
+    };
+    let (x, y) = camera.world_to_screen(position);
+    Rect::new(
+        x.round() as i32,
+        y.round() as i32,
+        (size.0 as f64 * camera.get_zoom()).round() as u32,
+        (size.1 as f64 * camera.get_zoom()).round() as u32,
+    )
+}
+
+/// Draws the tiles of the chunks that are in the camera viewport, layer by layer
+fn draw_tilemap(
+    tilemap: &TilemapComponent,
+    camera: Option<&CameraComponent>,
+    position: (f64, f64),
+    renderer: &mut dyn Renderer,
+    stats: &mut RenderStats,
+) -> Result<()> {
+    let (chunk_columns, chunk_rows) = tilemap.get_chunk_count();
+    let chunk_size = (
+        TILEMAP_CHUNK_SIZE * tilemap.tile_width,
+        TILEMAP_CHUNK_SIZE * tilemap.tile_height,
+    );
+
+    for layer in tilemap.layers.iter().filter(|layer| layer.is_visible) {
+        let origin = (position.0 + layer.offset.0, position.1 + layer.offset.1);
+        for chunk_row in 0..chunk_rows {
+            for chunk_column in 0..chunk_columns {
+                let chunk_position = (
+                    origin.0 + (chunk_column * chunk_size.0) as f64,
+                    origin.1 + (chunk_row * chunk_size.1) as f64,
+                );
+                let chunk_rect = to_screen_rect(camera, chunk_position, chunk_size);
+                if camera.is_some_and(|camera| !is_in_viewport(camera, chunk_rect)) {
+                    stats.chunks_culled += 1;
+                    continue;
+                }
+                stats.chunks_drawn += 1;
+
+                let first_row = chunk_row * TILEMAP_CHUNK_SIZE;
+                let first_column = chunk_column * TILEMAP_CHUNK_SIZE;
+                let last_row = (first_row + TILEMAP_CHUNK_SIZE).min(tilemap.height);
+                let last_column = (first_column + TILEMAP_CHUNK_SIZE).min(tilemap.width);
+                for row in first_row..last_row {
+                    for column in first_column..last_column {
+                        let index = (row * tilemap.width + column) as usize;
+                        let Some(&tile) = layer.tiles.get(index) else {
+                            continue;
+                        };
+                        let gid = get_tile_gid(tile);
+                        let Some(tileset) = tilemap.get_tileset(gid) else {
+                            continue;
+                        };
+                        let (x, y, width, height) = tileset.get_src_rect(gid);
+                        // Tiles bigger than the grid stick out at the top like in Tiled
+                        let tile_position = (
+                            origin.0 + (column * tilemap.tile_width) as f64,
+                            origin.1 + ((row + 1) * tilemap.tile_height) as f64 - height as f64,
+                        );
+                        let flip = TextureFlip {
+                            horizontal: tile & FLIPPED_HORIZONTALLY_FLAG != 0,
+                            vertical: tile & FLIPPED_VERTICALLY_FLAG != 0,
+                        };
+                        renderer.draw_texture(
+                            &tileset.texture_id,
+                            Some(Rect::new(x, y, width, height)),
+                            to_screen_rect(camera, tile_position, (width, height)),
+                            flip,
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn draw_shape(
+    shape: &ShapeComponent,
+    camera: Option<&CameraComponent>,
+    position: (f64, f64),
+    renderer: &mut dyn Renderer,
+) -> Result<()> {
+    let zoom = camera.map_or(1., CameraComponent::get_zoom);
+    let to_screen = |offset: (f64, f64)| {
+        let world = (position.0 + offset.0, position.1 + offset.1);
+        camera.map_or(world, |camera| camera.world_to_screen(world))
+    };
+    let (color, stroke_width) = (shape.color, shape.stroke_width);
+
+    match &shape.shape {
+        Shape::Circle { radius } if shape.filled => {
+            shapes::fill_circle(renderer, to_screen((0., 0.)), radius * zoom, color)
+        }
+        Shape::Circle { radius } => shapes::draw_circle(
+            renderer,
+            to_screen((0., 0.)),
+            radius * zoom,
+            stroke_width,
+            color,
+        ),
+        Shape::Line { end } => shapes::draw_line(
+            renderer,
+            to_screen((0., 0.)),
+            to_screen(*end),
+            stroke_width,
+            color,
+        ),
+        Shape::Polygon { points } => {
+            let points: Vec<(f64, f64)> = points.iter().map(|point| to_screen(*point)).collect();
+            if shape.filled {
+                shapes::fill_polygon(renderer, &points, color)
+            } else {
+                shapes::draw_polygon(renderer, &points, stroke_width, color)
+            }
+        }
+        Shape::Rect { width, height } => {
+            let (x, y) = to_screen((0., 0.));
+            let rect = Rect::new(
+                x.round() as i32,
+                y.round() as i32,
+                (width * zoom).round() as u32,
+                (height * zoom).round() as u32,
+            );
+            if shape.filled {
+                renderer.fill_rect(rect, color)
+            } else {
+                shapes::draw_rect(renderer, rect, stroke_width, color)
+            }
+        }
+    }
+}
+
+/// Each particle is a square centered on its position, colored and sized by its life
+fn draw_particles(
+    emitter: &ParticleEmitterComponent,
+    camera: Option<&CameraComponent>,
+    renderer: &mut dyn Renderer,
+) -> Result<()> {
+    for particle in &emitter.particles {
+        let life = particle.get_life();
+        let size = emitter.get_size_at(life);
+        if size <= 0. {
+            continue;
+        }
+        let top_left = (
+            particle.position.0 - size / 2.,
+            particle.position.1 - size / 2.,
+        );
+        let size = size.round().max(1.) as u32;
+        renderer.fill_rect(
+            to_screen_rect(camera, top_left, (size, size)),
+            emitter.get_color_at(life),
+        )?;
+    }
+    Ok(())
+}
+
+fn is_in_viewport(camera: &CameraComponent, rect: Rect) -> bool {
+    let (x, y, width, height) = camera.viewport;
+    rect.has_intersection(Rect::new(x, y, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::systems::SystemMaskBuilder,
+        renderer::{
+            asset_store::AssetStore,
+            recording_renderer::{DrawCall, RecordingRenderer},
+            screenshot::{compare_with_golden, Frame},
+            sdl_renderer::SdlRenderer,
+        },
+    };
+    use anyhow::Error;
+    use sdl2::{
+        pixels::{Color, PixelFormatEnum},
+        surface::Surface,
+    };
+    use std::path::Path;
+
+    const BACKGROUND: Color = Color::RGB(20, 24, 32);
+
+    fn create_registry() -> Result<Registry> {
+        let mut registry = Registry::default();
+        registry.register_component::<TransformComponent>()?;
+        registry.register_component::<RenderComponent>()?;
+        registry.register_component::<SpriteComponent>()?;
+        registry.register_component::<CameraComponent>()?;
+        let render_mask = SystemMaskBuilder::new(&registry)
+            .with::<TransformComponent>()?
+            .build();
+        registry.register_system::<RenderSystem>(render_mask)?;
+        let camera_mask = SystemMaskBuilder::new(&registry)
+            .with::<CameraComponent>()?
+            .build();
+        registry.register_system::<CameraSystem>(camera_mask)?;
+        Ok(registry)
+    }
+
+    fn add_rect(
+        registry: &mut Registry,
+        position: (f64, f64),
+        (width, height): (u32, u32),
+        color: Color,
+        layer: RenderLayer,
+        z_index: i32,
+    ) -> Result<Entity> {
+        let entity = registry.create_entity();
+        registry.add_component(entity, TransformComponent { position })?;
+        registry.add_component(
+            entity,
+            RenderComponent {
+                width,
+                height,
+                color,
+                layer,
+                z_index,
+            },
+        )?;
+        Ok(entity)
+    }
+
+    /// A 64x48 camera at 2x zoom looking at the world area (26, 22, 32, 24), a background
+    /// rectangle, two overlapping world rectangles, a culled one and a translucent ui bar
+    fn create_scene() -> Result<Registry> {
+        let mut registry = create_registry()?;
+        let camera = registry.create_entity();
+        registry.add_component(
+            camera,
+            CameraComponent {
+                position: (42., 34.),
+                zoom: 2.,
+                ..CameraComponent::new((0, 0, 64, 48))
+            },
+        )?;
+        add_rect(
+            &mut registry,
+            (30., 30.),
+            (12, 14),
+            Color::RGB(220, 200, 40),
+            RenderLayer::Background,
+            10,
+        )?;
+        add_rect(
+            &mut registry,
+            (28., 24.),
+            (8, 6),
+            Color::RGB(200, 40, 40),
+            RenderLayer::World,
+            0,
+        )?;
+        add_rect(
+            &mut registry,
+            (34., 28.),
+            (10, 8),
+            Color::RGB(40, 160, 220),
+            RenderLayer::World,
+            -1,
+        )?;
+        add_rect(
+            &mut registry,
+            (200., 200.),
+            (4, 4),
+            Color::RGB(40, 220, 40),
+            RenderLayer::World,
+            0,
+        )?;
+        add_rect(
+            &mut registry,
+            (0., 40.),
+            (64, 8),
+            Color::RGBA(255, 255, 255, 128),
+            RenderLayer::Ui,
+            0,
+        )?;
+        registry.update()?;
+        Ok(registry)
+    }
+
+    #[test]
+    fn rendered_scene_matches_the_golden_image() -> Result<()> {
+        let registry = create_scene()?;
+        let canvas = Surface::new(64, 48, PixelFormatEnum::RGBA32)
+            .map_err(Error::msg)?
+            .into_canvas()
+            .map_err(Error::msg)?;
+        let mut renderer = SdlRenderer::new(canvas, AssetStore::new());
+        renderer.clear(BACKGROUND)?;
+        RenderSystem::update(&registry, &mut renderer)?;
+
+        let frame = Frame::from_surface(&renderer.into_canvas().into_surface())?;
+        let golden_path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/render_system_scene.png");
+        compare_with_golden(&frame, &golden_path, 2)
+    }
+
+    #[test]
+    fn scene_is_drawn_as_recorded_draw_calls() -> Result<()> {
+        let mut registry = create_scene()?;
+        registry.add_resource(RenderStats::default());
+        let mut renderer = RecordingRenderer::new();
+        RenderSystem::update(&registry, &mut renderer)?;
+
+        assert_eq!(
+            renderer.get_draw_calls(),
+            [
+                DrawCall::FillRect {
+                    rect: Rect::new(8, 16, 24, 28),
+                    color: Color::RGB(220, 200, 40),
+                },
+                DrawCall::FillRect {
+                    rect: Rect::new(16, 12, 20, 16),
+                    color: Color::RGB(40, 160, 220),
+                },
+                DrawCall::FillRect {
+                    rect: Rect::new(4, 4, 16, 12),
+                    color: Color::RGB(200, 40, 40),
+                },
+                DrawCall::FillRect {
+                    rect: Rect::new(0, 40, 64, 8),
+                    color: Color::RGBA(255, 255, 255, 128),
+                },
+            ]
+        );
+        assert_eq!(
+            *registry.get_resource::<RenderStats>()?,
+            RenderStats {
+                drawn: 4,
+                culled: 1,
+                ..RenderStats::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sprites_are_drawn_with_their_source_rect_and_flip() -> Result<()> {
+        let mut registry = create_registry()?;
+        let entity = registry.create_entity();
+        registry.add_component(
+            entity,
+            TransformComponent {
+                position: (10., 20.),
+            },
+        )?;
+        registry.add_component(
+            entity,
+            SpriteComponent {
+                texture_id: "tank".to_string(),
+                width: 32,
+                height: 16,
+                src_rect: Some((16, 0, 16, 8)),
+                flip_horizontal: true,
+                flip_vertical: false,
+                layer: RenderLayer::World,
+                z_index: 0,
+            },
+        )?;
+        registry.update()?;
+
+        // Without a camera world and screen coordinates are the same
+        let mut renderer = RecordingRenderer::new();
+        RenderSystem::update(&registry, &mut renderer)?;
+        assert_eq!(
+            renderer.get_draw_calls(),
+            [DrawCall::DrawTexture {
+                texture_id: "tank".to_string(),
+                src: Some(Rect::new(16, 0, 16, 8)),
+                dst: Rect::new(10, 20, 32, 16),
+                flip: TextureFlip {
+                    horizontal: true,
+                    vertical: false,
+                },
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn draw_order_is_sorted_and_stable_between_frames() -> Result<()> {
+        let mut registry = create_registry()?;
+        let red = Color::RGB(255, 0, 0);
+        let green = Color::RGB(0, 255, 0);
+        let blue = Color::RGB(0, 0, 255);
+        let white = Color::RGB(255, 255, 255);
+        add_rect(&mut registry, (0., 0.), (1, 1), red, RenderLayer::Ui, 0)?;
+        add_rect(
+            &mut registry,
+            (1., 0.),
+            (1, 1),
+            green,
+            RenderLayer::World,
+            1,
+        )?;
+        add_rect(&mut registry, (2., 0.), (1, 1), blue, RenderLayer::World, 0)?;
+        add_rect(
+            &mut registry,
+            (3., 0.),
+            (1, 1),
+            white,
+            RenderLayer::World,
+            0,
+        )?;
+        // An entity with a sprite and a rectangle draws the rectangle first
+        let both = add_rect(
+            &mut registry,
+            (4., 0.),
+            (1, 1),
+            red,
+            RenderLayer::Effects,
+            0,
+        )?;
+        registry.add_component(
+            both,
+            SpriteComponent {
+                texture_id: "tank".to_string(),
+                width: 1,
+                height: 1,
+                src_rect: None,
+                flip_horizontal: false,
+                flip_vertical: false,
+                layer: RenderLayer::Effects,
+                z_index: 0,
+            },
+        )?;
+        registry.update()?;
+
+        let mut renderer = RecordingRenderer::new();
+        RenderSystem::update(&registry, &mut renderer)?;
+        let first_frame = renderer.take_draw_calls();
+        let fill = |x, color| DrawCall::FillRect {
+            rect: Rect::new(x, 0, 1, 1),
+            color,
+        };
+        assert_eq!(
+            first_frame,
+            [
+                fill(2, blue),
+                fill(3, white),
+                fill(1, green),
+                fill(4, red),
+                DrawCall::DrawTexture {
+                    texture_id: "tank".to_string(),
+                    src: None,
+                    dst: Rect::new(4, 0, 1, 1),
+                    flip: TextureFlip::default(),
+                },
+                fill(0, red),
+            ]
+        );
+
+        for _ in 0..10 {
+            RenderSystem::update(&registry, &mut renderer)?;
+            assert_eq!(renderer.take_draw_calls(), first_frame);
+        }
+        Ok(())
+    }
+}
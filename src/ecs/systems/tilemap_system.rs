@@ -0,0 +1,32 @@
+use crate::ecs::{
+    components::{TilemapComponent, TransformComponent, TILE_SOLID},
+    registry::Registry,
+};
+use anyhow::Result;
+
+/// Tile queries for gameplay code, tilemaps are drawn by RenderSystem
+pub struct TilemapSystem;
+
+impl TilemapSystem {
+    /// True if any tilemap has a solid tile under the world position
+    pub fn is_solid_at(registry: &Registry, world: (f64, f64)) -> Result<bool> {
+        Self::has_flags_at(registry, world, TILE_SOLID)
+    }
+
+    /// NOTE: Without the system registered there are no tilemaps to query
+    pub fn has_flags_at(registry: &Registry, world: (f64, f64), flags: u32) -> Result<bool> {
+        if !registry.has_system::<TilemapSystem>() {
+            return Ok(false);
+        }
+        for entity in registry.get_system_entities::<TilemapSystem>()? {
+            let origin = registry
+                .get_component::<TransformComponent>(entity)?
+                .position;
+            let tilemap = registry.get_component::<TilemapComponent>(entity)?;
+            if tilemap.has_flags_at(origin, world, flags) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
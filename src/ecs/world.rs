@@ -0,0 +1,136 @@
+use super::{
+    ecs_errors::EcsErrors,
+    registry::{Entity, Registry},
+};
+use crate::logger::Logger;
+use anyhow::Result;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+
+/// Independent registries side by side (game, menu, editor preview...), one of them active.
+/// Every world registers its own components and systems.
+pub struct Worlds {
+    logger: Rc<RefCell<Logger>>,
+    worlds: BTreeMap<String, Registry>,
+    active_world: String,
+}
+
+impl Worlds {
+    /// Creates the first world and makes it the active one
+    pub fn new(logger: Rc<RefCell<Logger>>, active_world: &str) -> Self {
+        let mut worlds = BTreeMap::new();
+        worlds.insert(active_world.to_string(), Registry::new(Rc::clone(&logger)));
+        Self {
+            logger,
+            worlds,
+            active_world: active_world.to_string(),
+        }
+    }
+
+    pub fn create_world(&mut self, name: &str) -> Result<&mut Registry> {
+        if self.worlds.contains_key(name) {
+            return Err(EcsErrors::WorldNameTaken(name.to_string()).into());
+        }
+        self.logger
+            .as_ref()
+            .borrow_mut()
+            .log(&format!("World {} is created", name));
+        Ok(self
+            .worlds
+            .entry(name.to_string())
+            .or_insert_with(|| Registry::new(Rc::clone(&self.logger))))
+    }
+
+    /// NOTE: The active world can not be removed, switch to another one first
+    pub fn remove_world(&mut self, name: &str) -> Result<Registry> {
+        if name == self.active_world {
+            return Err(EcsErrors::WorldIsActive(name.to_string()).into());
+        }
+        let world = self
+            .worlds
+            .remove(name)
+            .ok_or_else(|| EcsErrors::WorldDoesNotExist(name.to_string()))?;
+        self.logger
+            .as_ref()
+            .borrow_mut()
+            .log(&format!("World {} is removed", name));
+        Ok(world)
+    }
+
+    pub fn has_world(&self, name: &str) -> bool {
+        self.worlds.contains_key(name)
+    }
+
+    pub fn get_world(&self, name: &str) -> Result<&Registry> {
+        self.worlds
+            .get(name)
+            .ok_or_else(|| EcsErrors::WorldDoesNotExist(name.to_string()).into())
+    }
+
+    pub fn get_world_mut(&mut self, name: &str) -> Result<&mut Registry> {
+        self.worlds
+            .get_mut(name)
+            .ok_or_else(|| EcsErrors::WorldDoesNotExist(name.to_string()).into())
+    }
+
+    pub fn get_world_names(&self) -> Vec<&str> {
+        self.worlds.keys().map(String::as_str).collect()
+    }
+
+    pub fn set_active_world(&mut self, name: &str) -> Result<()> {
+        if !self.worlds.contains_key(name) {
+            return Err(EcsErrors::WorldDoesNotExist(name.to_string()).into());
+        }
+        self.active_world = name.to_string();
+        Ok(())
+    }
+
+    pub fn get_active_world_name(&self) -> &str {
+        &self.active_world
+    }
+
+    pub fn get_active_world(&self) -> &Registry {
+        &self.worlds[&self.active_world]
+    }
+
+    pub fn get_active_world_mut(&mut self) -> &mut Registry {
+        self.worlds
+            .get_mut(&self.active_world)
+            .expect("active world is never removed")
+    }
+
+    pub fn into_active_world(mut self) -> Registry {
+        self.worlds
+            .remove(&self.active_world)
+            .expect("active world is never removed")
+    }
+
+    /// Moves the entities (and their children) from one world to another.
+    /// Returns old id => new id, see `Registry::move_entities`.
+    pub fn move_entities(
+        &mut self,
+        from: &str,
+        to: &str,
+        entities: &[Entity],
+    ) -> Result<HashMap<Entity, Entity>> {
+        if from == to {
+            return Err(EcsErrors::CannotMoveToSameWorld(from.to_string()).into());
+        }
+        if !self.worlds.contains_key(to) {
+            return Err(EcsErrors::WorldDoesNotExist(to.to_string()).into());
+        }
+        let mut source = self
+            .worlds
+            .remove(from)
+            .ok_or_else(|| EcsErrors::WorldDoesNotExist(from.to_string()))?;
+
+        let destination = self.worlds.get_mut(to).expect("checked above");
+        let entity_map = source.move_entities(entities, destination);
+
+        self.worlds.insert(from.to_string(), source);
+        entity_map
+    }
+}
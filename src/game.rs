@@ -1,34 +1,217 @@
 use crate::{
+    debug_overlay::DebugOverlay,
     ecs::{
-        components::{RenderComponent, TransformComponent, VelocityComponent},
-        registry::Registry,
+        components::{
+            AnimationComponent, BoxColliderComponent, CameraComponent, NameComponent,
+            ParentComponent, ParticleEmitterComponent, RenderComponent, ShapeComponent,
+            SpriteComponent, TilemapComponent, TransformComponent, VelocityComponent,
+        },
+        ecs_errors::EcsErrors,
+        prefab::{get_prefab_paths, load_prefab_file},
+        reflect::Reflect,
+        registry::{Entity, Registry},
+        rollback::RollbackBuffer,
         systems::{
-            movement_system::MovementSystem, render_system::RenderSystem, SystemMaskBuilder,
+            animation_system::{AnimationEvents, AnimationSystem},
+            camera_system::{CameraSystem, MapSize},
+            debug_draw_system::{DebugDrawSettings, DebugDrawSystem},
+            movement_system::MovementSystem,
+            particle_system::ParticleSystem,
+            render_system::{RenderStats, RenderSystem},
+            tilemap_system::TilemapSystem,
+            SystemMaskBuilder,
         },
+        world::Worlds,
+    },
+    input::ScriptedInput,
+    level::{
+        level_errors::LevelErrors,
+        loader::{get_level_path, load_level_file, Level, DEFAULT_BACKGROUND_COLOR},
+        watcher::AssetWatcher,
     },
     logger::Logger,
+    renderer::{
+        asset_store::{get_image_paths, AssetStore},
+        screenshot::Frame,
+        sdl_renderer::SdlRenderer,
+        Renderer,
+    },
+    window::WindowInfo,
 };
 use anyhow::{Error, Result};
-use sdl2::{event::Event, keyboard::Keycode, pixels::Color, rect::Rect, render::WindowCanvas, Sdl};
+use sdl2::{
+    event::{Event, WindowEvent},
+    image::{InitFlag, Sdl2ImageContext},
+    keyboard::Keycode,
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    surface::Surface,
+    video::{FullscreenType, Window},
+    Sdl,
+};
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
+    any::Any,
     cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
     rc::Rc,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 
-// TODO: map_width and height static mut is unsafe
+/// Size of the window when the game starts, it can be resized
 pub const WINDOW_WIDTH: u32 = 800;
 pub const WINDOW_HEIGHT: u32 = 600;
+/// Resolution the game draws in, it is scaled to the window, see WindowInfo
+pub const VIRTUAL_WIDTH: u32 = 800;
+pub const VIRTUAL_HEIGHT: u32 = 600;
+/// Color of the bars around the virtual screen when the aspect ratios do not match
+pub const LETTERBOX_COLOR: Color = Color::RGB(0, 0, 0);
 pub const FPS: u128 = 60;
 pub const MILLISECS_PER_FRAME: u128 = 1000 / FPS;
+/// Every update advances time by the same amount, so frames simulated again after a
+/// rollback give the same result. Headless games do not wait for frames.
+pub const FIXED_DELTA_TIME: f64 = MILLISECS_PER_FRAME as f64 / 1000.0;
+/// A slow frame runs at most this many updates to catch up, the rest of the time is dropped
+pub const MAX_UPDATES_PER_FRAME: u32 = 5;
+pub const ASSETS_DIR: &str = "assets";
+pub const FONT_FILE: &str = "fonts/DejaVuSansMono.ttf";
+pub const FONT_SIZE: u16 = 14;
+pub const DEBUG_OVERLAY_KEY: Keycode = Keycode::F3;
+pub const DEBUG_DRAW_COLLIDERS_KEY: Keycode = Keycode::F4;
+pub const DEBUG_DRAW_VELOCITIES_KEY: Keycode = Keycode::F5;
+pub const DEBUG_DRAW_LABELS_KEY: Keycode = Keycode::F6;
+pub const FULLSCREEN_KEY: Keycode = Keycode::F11;
+pub const MAIN_WORLD: &str = "game";
+pub const HOT_RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+/// Number of past frames the active world can be rolled back to
+pub const ROLLBACK_FRAMES: usize = 120;
+/// Root entities spawned by the loaded level are in this group. Killed entities leave
+/// their groups, so an entity id reused by something else is never despawned by mistake.
+pub const LEVEL_GROUP: &str = "level";
+
+/// A system level files can list by name. The mask is built before the system is
+/// registered, so a level either registers all of its systems or none of them.
+pub struct SystemEntry {
+    pub name: &'static str,
+    pub get_mask: fn(&Registry) -> Result<u32>,
+    pub register: fn(&mut Registry, u32) -> Result<bool>,
+}
+
+pub static SYSTEMS: [SystemEntry; 7] = [
+    SystemEntry {
+        name: "MovementSystem",
+        get_mask: |registry| {
+            Ok(SystemMaskBuilder::new(registry)
+                .with::<TransformComponent>()?
+                .with::<VelocityComponent>()?
+                .build())
+        },
+        register: Registry::register_system::<MovementSystem>,
+    },
+    SystemEntry {
+        name: "AnimationSystem",
+        get_mask: |registry| {
+            Ok(SystemMaskBuilder::new(registry)
+                .with::<AnimationComponent>()?
+                .with::<SpriteComponent>()?
+                .build())
+        },
+        register: Registry::register_system::<AnimationSystem>,
+    },
+    SystemEntry {
+        name: "ParticleSystem",
+        get_mask: |registry| {
+            Ok(SystemMaskBuilder::new(registry)
+                .with::<TransformComponent>()?
+                .with::<ParticleEmitterComponent>()?
+                .build())
+        },
+        register: Registry::register_system::<ParticleSystem>,
+    },
+    SystemEntry {
+        name: "CameraSystem",
+        get_mask: |registry| {
+            Ok(SystemMaskBuilder::new(registry)
+                .with::<CameraComponent>()?
+                .build())
+        },
+        register: Registry::register_system::<CameraSystem>,
+    },
+    SystemEntry {
+        name: "TilemapSystem",
+        get_mask: |registry| {
+            Ok(SystemMaskBuilder::new(registry)
+                .with::<TransformComponent>()?
+                .with::<TilemapComponent>()?
+                .build())
+        },
+        register: Registry::register_system::<TilemapSystem>,
+    },
+    // Entities without anything to draw are skipped
+    SystemEntry {
+        name: "RenderSystem",
+        get_mask: |registry| {
+            Ok(SystemMaskBuilder::new(registry)
+                .with::<TransformComponent>()?
+                .build())
+        },
+        register: Registry::register_system::<RenderSystem>,
+    },
+    // Components are checked per category when drawing
+    SystemEntry {
+        name: "DebugDrawSystem",
+        get_mask: |registry| {
+            Ok(SystemMaskBuilder::new(registry)
+                .with::<TransformComponent>()?
+                .build())
+        },
+        register: Registry::register_system::<DebugDrawSystem>,
+    },
+];
+
+pub fn get_system_entry(name: &str) -> Option<&'static SystemEntry> {
+    SYSTEMS.iter().find(|system| system.name == name)
+}
+
+struct LoadedLevel {
+    level: Level,
+    /// entities[i] was spawned from level.entities[i]
+    entities: Vec<Entity>,
+}
+
+enum Backend {
+    Window {
+        sdl_context: Sdl,
+        _image_context: Sdl2ImageContext,
+        renderer: SdlRenderer<Window>,
+    },
+    /// No window and no SDL, input comes from a script and rendering is skipped
+    Headless { input: ScriptedInput },
+}
 
 pub struct Game {
     is_running: bool,
     prev_frame_time: SystemTime,
+    /// Real time in seconds that has not been simulated yet
+    unsimulated_time: f64,
     logger: Rc<RefCell<Logger>>,
-    registry: Registry,
-    sdl_context: Sdl,
-    canvas: WindowCanvas,
+    worlds: Worlds,
+    background_color: Color,
+    loaded_level: Option<LoadedLevel>,
+    prefab_paths: BTreeSet<PathBuf>,
+    asset_watcher: AssetWatcher,
+    /// key => texture id, images in assets/images and the tileset images of the level
+    texture_paths: BTreeMap<String, PathBuf>,
+    debug_overlay: DebugOverlay,
+    debug_draw: DebugDrawSettings,
+    /// Copied into the WindowInfo resource of every world when it changes
+    window_info: WindowInfo,
+    /// Number of updates since setup, rolling back rewinds it
+    frame: u64,
+    /// Snapshots of the active world after each frame
+    rollback: RollbackBuffer,
+    backend: Backend,
 }
 
 impl Game {
@@ -39,24 +222,58 @@ impl Game {
         let window = video_subsystem
             .window(title, WINDOW_WIDTH, WINDOW_HEIGHT)
             .position_centered()
+            .resizable()
             .opengl()
             .build()
             .map_err(Error::msg)?;
 
-        let canvas = window.into_canvas().build().map_err(Error::msg)?;
+        let mut canvas = window.into_canvas().build().map_err(Error::msg)?;
+        // SDL scales the drawing and the mouse positions, and letterboxes the rest
+        canvas
+            .set_logical_size(VIRTUAL_WIDTH, VIRTUAL_HEIGHT)
+            .map_err(Error::msg)?;
+        let image_context = sdl2::image::init(InitFlag::PNG).map_err(Error::msg)?;
+
+        Ok(Self::with_backend(
+            Backend::Window {
+                sdl_context,
+                _image_context: image_context,
+                renderer: SdlRenderer::new(
+                    canvas,
+                    AssetStore::new().with_font_path(&get_font_path(), FONT_SIZE),
+                ),
+            },
+            (WINDOW_WIDTH, WINDOW_HEIGHT),
+        ))
+    }
+
+    /// Runs without a window, see `run_headless`. The window size is the virtual resolution.
+    pub fn new_headless(input: ScriptedInput) -> Self {
+        Self::with_backend(Backend::Headless { input }, (VIRTUAL_WIDTH, VIRTUAL_HEIGHT))
+    }
+
+    fn with_backend(backend: Backend, window_size: (u32, u32)) -> Self {
         let logger = Rc::new(RefCell::new(Logger::default()));
-        let registry = Registry::new(Rc::clone(&logger));
+        let worlds = Worlds::new(Rc::clone(&logger), MAIN_WORLD);
 
-        let game = Self {
+        Self {
             is_running: false,
             prev_frame_time: SystemTime::now(),
-            registry,
+            unsimulated_time: 0.,
+            worlds,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            loaded_level: None,
+            prefab_paths: BTreeSet::new(),
+            asset_watcher: AssetWatcher::new(HOT_RELOAD_INTERVAL),
+            texture_paths: BTreeMap::new(),
+            debug_overlay: DebugOverlay::new(),
+            debug_draw: DebugDrawSettings::default(),
+            window_info: WindowInfo::new(window_size, (VIRTUAL_WIDTH, VIRTUAL_HEIGHT)),
+            frame: 0,
+            rollback: RollbackBuffer::new(ROLLBACK_FRAMES),
             logger,
-            canvas,
-            sdl_context,
-        };
-
-        Ok(game)
+            backend,
+        }
     }
 
     pub fn run(&mut self) -> Result<()> {
@@ -71,35 +288,70 @@ impl Game {
         Ok(())
     }
 
+    /// Runs setup and `frames` frames with a fixed delta time, then returns the active world.
+    /// The game stops early if the scripted input quits.
+    pub fn run_headless(mut self, frames: u64) -> Result<Registry> {
+        self.setup()?;
+        self.run_frames(frames)?;
+        Ok(self.worlds.into_active_world())
+    }
+
+    /// Runs `frames` frames with a fixed delta time, call setup first
+    pub fn run_frames(&mut self, frames: u64) -> Result<()> {
+        for _ in 0..frames {
+            if !self.is_running {
+                break;
+            }
+            self.process_input()?;
+            self.debug_overlay.record_frame(FIXED_DELTA_TIME);
+            self.update_systems(FIXED_DELTA_TIME)?;
+            self.render()?;
+        }
+        Ok(())
+    }
+
     pub fn setup(&mut self) -> Result<()> {
         self.logger
             .as_ref()
             .borrow_mut()
             .log("Game setup is called");
+
+        let registry = self.worlds.get_active_world_mut();
+        Self::register_components(registry)?;
+        registry.add_resource(RenderStats::default());
+        registry.add_resource(self.window_info);
+        // Debug drawing works in every level, whether the level lists it or not
+        Self::register_system_by_name(registry, "DebugDrawSystem")?;
+
+        let prefabs_dir = Path::new(ASSETS_DIR).join("prefabs");
+        for path in get_prefab_paths(&prefabs_dir)? {
+            registry.register_prefab(load_prefab_file(&path, registry)?);
+            self.asset_watcher.watch(&path);
+            self.prefab_paths.insert(path);
+        }
+
+        let images_dir = Path::new(ASSETS_DIR).join("images");
+        for (texture_id, path) in get_image_paths(&images_dir)? {
+            self.add_texture_path(&texture_id, &path);
+        }
+
         self.load_level(1)?;
+        if let Backend::Window { renderer, .. } = &mut self.backend {
+            renderer.load_all_textures()?;
+        }
         self.prev_frame_time = SystemTime::now();
         self.is_running = true;
         Ok(())
     }
 
+    /// Replaces the entities of the previously loaded level, the rest of the world is kept
     pub fn load_level(&mut self, level: i32) -> Result<()> {
-        self.registry.register_component::<TransformComponent>()?;
-        self.registry.register_component::<RenderComponent>()?;
-        self.registry.register_component::<VelocityComponent>()?;
-
-        self.registry.register_system::<MovementSystem>(
-            SystemMaskBuilder::new(&self.registry)
-                .with::<TransformComponent>()?
-                .with::<VelocityComponent>()?
-                .build(),
-        )?;
-
-        self.registry.register_system::<RenderSystem>(
-            SystemMaskBuilder::new(&self.registry)
-                .with::<TransformComponent>()?
-                .with::<RenderComponent>()?
-                .build(),
-        )?;
+        let path = get_level_path(Path::new(ASSETS_DIR), level);
+        if let Some(loaded_level) = &self.loaded_level {
+            self.asset_watcher.unwatch(&loaded_level.level.path);
+        }
+        self.apply_level_file(&path)?;
+        self.asset_watcher.watch(&path);
 
         self.logger
             .as_ref()
@@ -109,14 +361,213 @@ impl Game {
         Ok(())
     }
 
+    fn apply_level_file(&mut self, path: &Path) -> Result<()> {
+        let registry = self.worlds.get_active_world_mut();
+        let level_data = load_level_file(path, registry)?;
+
+        let mut systems = vec![];
+        for system in &level_data.systems {
+            let entry =
+                get_system_entry(system).ok_or_else(|| LevelErrors::SystemDoesNotExist {
+                    file: path.display().to_string(),
+                    system: system.clone(),
+                })?;
+            systems.push((entry, (entry.get_mask)(registry)?));
+        }
+
+        // The loaded level stays as it is unless the new one spawns completely, its
+        // systems are only registered then
+        let entities = level_data.spawn(registry)?;
+        for (entry, mask) in systems {
+            (entry.register)(registry, mask)?;
+        }
+        if let Some(loaded_level) = &self.loaded_level {
+            for entity in &loaded_level.entities {
+                if registry.entity_belongs_to_group(*entity, LEVEL_GROUP) {
+                    registry.kill_entity(*entity)?;
+                }
+            }
+        }
+        for entity in &entities {
+            registry.add_entity_to_group(*entity, LEVEL_GROUP)?;
+        }
+        match level_data.map_size {
+            Some((width, height)) => registry.add_resource(MapSize { width, height }),
+            None => {
+                registry.remove_resource::<MapSize>();
+            }
+        }
+        for tileset in &level_data.tilesets {
+            if let Some(image) = &tileset.image {
+                self.add_texture_path(&tileset.name, image);
+            }
+        }
+        self.background_color = level_data.background_color;
+        self.loaded_level = Some(LoadedLevel {
+            level: level_data,
+            entities,
+        });
+        self.reset_rollback()
+    }
+
+    /// Respawns the level entities that are instances of the prefab in `path`
+    fn apply_prefab_file(&mut self, path: &Path) -> Result<()> {
+        let registry = self.worlds.get_active_world_mut();
+        let prefab = load_prefab_file(path, registry)?;
+        let prefab_name = prefab.name.clone();
+        let old_prefab = registry.get_prefab(&prefab_name).cloned();
+        registry.register_prefab(prefab);
+
+        let Some(loaded_level) = &mut self.loaded_level else {
+            return Ok(());
+        };
+        // All instances are respawned before the old ones are killed, if one fails the
+        // new instances are killed and the old prefab is registered again
+        let mut respawned = vec![];
+        for (index, level_entity) in loaded_level.level.entities.iter().enumerate() {
+            let entity = loaded_level.entities[index];
+            if level_entity.prefab.as_deref() != Some(&prefab_name)
+                || !registry.entity_belongs_to_group(entity, LEVEL_GROUP)
+            {
+                continue;
+            }
+            match loaded_level.level.spawn_entity(index, registry) {
+                Ok(new_entity) => respawned.push((index, new_entity)),
+                Err(err) => {
+                    for (_, new_entity) in respawned {
+                        registry.kill_entity(new_entity)?;
+                    }
+                    if let Some(old_prefab) = old_prefab {
+                        registry.register_prefab(old_prefab);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        for (index, new_entity) in respawned {
+            registry.kill_entity(loaded_level.entities[index])?;
+            registry.add_entity_to_group(new_entity, LEVEL_GROUP)?;
+            loaded_level.entities[index] = new_entity;
+        }
+        // Rolling back would bring the old instances back
+        self.reset_rollback()
+    }
+
+    /// Errors are logged instead of returned so a typo in a file does not end the game
+    fn hot_reload(&mut self) {
+        for path in self.asset_watcher.poll() {
+            let result = if self.prefab_paths.contains(&path) {
+                self.apply_prefab_file(&path)
+            } else {
+                self.apply_level_file(&path)
+            };
+
+            let mut logger = self.logger.as_ref().borrow_mut();
+            match result {
+                Ok(()) => logger.log(&format!("{} is reloaded", path.display())),
+                Err(err) => {
+                    logger.error(&format!("Could not reload {}: {:#}", path.display(), err))
+                }
+            }
+        }
+    }
+
+    /// Textures are loaded by the renderers when they are first drawn
+    pub fn add_texture_path(&mut self, texture_id: &str, path: &Path) {
+        self.texture_paths
+            .insert(texture_id.to_string(), path.to_path_buf());
+        if let Backend::Window { renderer, .. } = &mut self.backend {
+            renderer
+                .get_asset_store_mut()
+                .add_texture_path(texture_id, path);
+        }
+    }
+
+    /// Every world needs the same component types to move entities between them
+    pub fn create_world(&mut self, name: &str) -> Result<()> {
+        let registry = self.worlds.create_world(name)?;
+        Self::register_components(registry)?;
+        Self::register_systems(registry)?;
+        registry.add_resource(RenderStats::default());
+        registry.add_resource(self.window_info);
+        Ok(())
+    }
+
+    /// NOTE: Component masks follow the registration order, so it is part of the snapshot
+    /// schema
+    fn register_components(registry: &mut Registry) -> Result<()> {
+        Self::register_reflected_component::<TransformComponent>(registry)?;
+        Self::register_reflected_component::<RenderComponent>(registry)?;
+        Self::register_reflected_component::<SpriteComponent>(registry)?;
+        Self::register_reflected_component::<AnimationComponent>(registry)?;
+        Self::register_reflected_component::<CameraComponent>(registry)?;
+        Self::register_reflected_component::<BoxColliderComponent>(registry)?;
+        Self::register_reflected_component::<ShapeComponent>(registry)?;
+        Self::register_reflected_component::<VelocityComponent>(registry)?;
+        Self::register_data_component::<NameComponent>(registry)?;
+        Self::register_data_component::<ParentComponent>(registry)?;
+        Self::register_reflected_component::<TilemapComponent>(registry)?;
+        Self::register_reflected_component::<ParticleEmitterComponent>(registry)?;
+        // Resources that change with the simulation are part of snapshots and rollback
+        registry.register_resource_serializer::<MapSize>();
+        registry.register_resource_serializer::<AnimationEvents>();
+        Ok(())
+    }
+
+    /// Every component can be saved in snapshots, loaded from level and prefab files and
+    /// cloned
+    fn register_data_component<T: Any + Clone + Serialize + DeserializeOwned>(
+        registry: &mut Registry,
+    ) -> Result<()> {
+        registry.register_component::<T>()?;
+        registry.register_component_serializer::<T>()?;
+        registry.register_component_cloner::<T>()
+    }
+
+    /// A data component whose fields can be inspected and set from the console too
+    fn register_reflected_component<T: Reflect + Clone + Serialize + DeserializeOwned>(
+        registry: &mut Registry,
+    ) -> Result<()> {
+        Self::register_data_component::<T>(registry)?;
+        registry.register_component_reflection::<T>()
+    }
+
+    fn register_systems(registry: &mut Registry) -> Result<()> {
+        for system in &SYSTEMS {
+            (system.register)(registry, (system.get_mask)(registry)?)?;
+        }
+        Ok(())
+    }
+
+    /// Level files list their active systems by name
+    fn register_system_by_name(registry: &mut Registry, system: &str) -> Result<()> {
+        let entry = get_system_entry(system).ok_or(EcsErrors::SystemDoesNotExist)?;
+        (entry.register)(registry, (entry.get_mask)(registry)?)?;
+        Ok(())
+    }
+
     pub fn process_input(&mut self) -> Result<()> {
-        let mut event_pump = self.sdl_context.event_pump().map_err(Error::msg)?;
+        let events: Vec<Event> = match &mut self.backend {
+            Backend::Window { sdl_context, .. } => {
+                let mut event_pump = sdl_context.event_pump().map_err(Error::msg)?;
+                event_pump.poll_iter().collect()
+            }
+            Backend::Headless { input } => input.get_events(self.frame),
+        };
 
-        for event in event_pump.poll_iter() {
+        for event in events {
             match event {
                 Event::Quit { .. } => {
                     self.is_running = false;
                 }
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(width, height),
+                    ..
+                } => {
+                    self.window_info.width = width.max(0) as u32;
+                    self.window_info.height = height.max(0) as u32;
+                    self.update_window_info()?;
+                }
                 Event::KeyDown {
                     keycode: Some(keycode),
                     ..
@@ -128,6 +579,17 @@ impl Game {
                     Keycode::Escape => {
                         self.is_running = false;
                     }
+                    DEBUG_OVERLAY_KEY => self.debug_overlay.toggle(),
+                    DEBUG_DRAW_COLLIDERS_KEY => {
+                        self.debug_draw.colliders = !self.debug_draw.colliders;
+                    }
+                    DEBUG_DRAW_VELOCITIES_KEY => {
+                        self.debug_draw.velocities = !self.debug_draw.velocities;
+                    }
+                    DEBUG_DRAW_LABELS_KEY => {
+                        self.debug_draw.labels = !self.debug_draw.labels;
+                    }
+                    FULLSCREEN_KEY => self.toggle_fullscreen()?,
                     _ => {}
                 },
                 _ => {}
@@ -136,6 +598,40 @@ impl Game {
         Ok(())
     }
 
+    /// Uses the desktop resolution, the new window size comes with the next resize event
+    pub fn toggle_fullscreen(&mut self) -> Result<()> {
+        let is_fullscreen = !self.window_info.is_fullscreen;
+        if let Backend::Window { renderer, .. } = &mut self.backend {
+            let fullscreen_type = if is_fullscreen {
+                FullscreenType::Desktop
+            } else {
+                FullscreenType::Off
+            };
+            renderer
+                .get_canvas_mut()
+                .window_mut()
+                .set_fullscreen(fullscreen_type)
+                .map_err(Error::msg)?;
+        }
+        self.window_info.is_fullscreen = is_fullscreen;
+        self.update_window_info()
+    }
+
+    fn update_window_info(&mut self) -> Result<()> {
+        let names: Vec<String> = self
+            .worlds
+            .get_world_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        for name in names {
+            self.worlds
+                .get_world_mut(&name)?
+                .add_resource(self.window_info);
+        }
+        Ok(())
+    }
+
     pub fn update(&mut self) -> Result<()> {
         let wait_time = MILLISECS_PER_FRAME.saturating_sub(
             SystemTime::now()
@@ -154,29 +650,343 @@ impl Game {
             / 1000.0;
 
         self.prev_frame_time = SystemTime::now();
+        self.debug_overlay.record_frame(dt);
 
+        self.hot_reload();
+        // The systems always get FIXED_DELTA_TIME, a frame runs as many updates as fit
+        // in the time that has passed
+        self.unsimulated_time =
+            (self.unsimulated_time + dt).min(MAX_UPDATES_PER_FRAME as f64 * FIXED_DELTA_TIME);
+        while self.unsimulated_time >= FIXED_DELTA_TIME {
+            self.unsimulated_time -= FIXED_DELTA_TIME;
+            self.update_systems(FIXED_DELTA_TIME)?;
+        }
+        Ok(())
+    }
+
+    fn update_systems(&mut self, dt: f64) -> Result<()> {
         // TODO: check this out
-        MovementSystem::update(&mut self.registry, dt)?;
+        let registry = self.worlds.get_active_world_mut();
+        if registry.has_system::<MovementSystem>() {
+            let start = Instant::now();
+            MovementSystem::update(registry, dt)?;
+            self.debug_overlay
+                .record_system_time("MovementSystem", start.elapsed());
+        }
+        if registry.has_system::<AnimationSystem>() {
+            let start = Instant::now();
+            AnimationSystem::update(registry, dt)?;
+            self.debug_overlay
+                .record_system_time("AnimationSystem", start.elapsed());
+        }
+        if registry.has_system::<ParticleSystem>() {
+            let start = Instant::now();
+            ParticleSystem::update(registry, dt)?;
+            self.debug_overlay
+                .record_system_time("ParticleSystem", start.elapsed());
+        }
+        // Cameras follow their targets after everything else has moved
+        if registry.has_system::<CameraSystem>() {
+            let start = Instant::now();
+            CameraSystem::update(registry, dt)?;
+            self.debug_overlay
+                .record_system_time("CameraSystem", start.elapsed());
+        }
 
         // Process adding/killing entities to the system by their mask
-        self.registry.update();
+        registry.update()?;
+
+        self.frame += 1;
+        self.rollback.save(self.frame, registry)
+    }
 
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Restores the active world to how it was after `frame` updates, the next updates
+    /// simulate the following frames again with the same FIXED_DELTA_TIME. Only the last ROLLBACK_FRAMES frames are kept
+    /// and loading a level drops the frames before it.
+    /// NOTE: Resources without a serializer (RenderStats, WindowInfo) are not rolled back
+    pub fn rollback_to(&mut self, frame: u64) -> Result<()> {
+        self.rollback
+            .restore(frame, self.worlds.get_active_world_mut())?;
+        self.frame = frame;
         Ok(())
     }
 
+    /// Snapshots of another level have a different schema, they can not be restored
+    fn reset_rollback(&mut self) -> Result<()> {
+        self.rollback.clear();
+        self.rollback
+            .save(self.frame, self.worlds.get_active_world())
+    }
+
     pub fn render(&mut self) -> Result<()> {
-        self.canvas.set_draw_color(Color::RGB(30, 30, 30));
-        self.canvas.clear();
+        let Backend::Window { renderer, .. } = &mut self.backend else {
+            return Ok(());
+        };
 
-        self.canvas.set_draw_color(Color::RGB(255, 30, 30));
+        let start = Instant::now();
+        Self::draw_world(
+            self.worlds.get_active_world(),
+            self.background_color,
+            renderer,
+        )?;
+        self.debug_overlay
+            .record_system_time("RenderSystem", start.elapsed());
+        let registry = self.worlds.get_active_world();
+        if registry.has_system::<DebugDrawSystem>() {
+            DebugDrawSystem::update(registry, &self.debug_draw, renderer)?;
+        }
+        self.debug_overlay
+            .draw(registry, &self.logger.borrow(), renderer)?;
+        renderer.present()
+    }
 
-        self
-            .canvas
-            .fill_rect(Rect::new(10, 10, 20, 20))
+    /// Renders the active world with SDL's software renderer into memory, no window or GPU
+    /// is needed so it works for headless games too
+    pub fn render_frame(&self) -> Result<Frame> {
+        let surface = Surface::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT, PixelFormatEnum::RGBA32)
             .map_err(Error::msg)?;
+        let mut renderer = SdlRenderer::new(
+            surface.into_canvas().map_err(Error::msg)?,
+            AssetStore::with_texture_paths(self.texture_paths.clone())
+                .with_font_path(&get_font_path(), FONT_SIZE),
+        );
+
+        Self::draw_world(
+            self.worlds.get_active_world(),
+            self.background_color,
+            &mut renderer,
+        )?;
+        let registry = self.worlds.get_active_world();
+        if registry.has_system::<DebugDrawSystem>() {
+            DebugDrawSystem::update(registry, &self.debug_draw, &mut renderer)?;
+        }
+        self.debug_overlay
+            .draw(registry, &self.logger.borrow(), &mut renderer)?;
+        renderer.present()?;
+
+        Frame::from_surface(&renderer.into_canvas().into_surface())
+    }
+
+    pub fn get_debug_overlay(&self) -> &DebugOverlay {
+        &self.debug_overlay
+    }
+
+    pub fn get_debug_overlay_mut(&mut self) -> &mut DebugOverlay {
+        &mut self.debug_overlay
+    }
+
+    pub fn get_debug_draw_settings(&self) -> &DebugDrawSettings {
+        &self.debug_draw
+    }
+
+    pub fn get_debug_draw_settings_mut(&mut self) -> &mut DebugDrawSettings {
+        &mut self.debug_draw
+    }
+
+    pub fn get_window_info(&self) -> &WindowInfo {
+        &self.window_info
+    }
+
+    /// Converts a position in the virtual resolution (e.g. the mouse) to a world position
+    /// through the active camera, without a camera both are the same.
+    /// NOTE: Window positions have to go through WindowInfo::window_to_virtual first
+    pub fn screen_to_world(&self, screen: (i32, i32)) -> Result<(f64, f64)> {
+        let screen = (screen.0 as f64, screen.1 as f64);
+        let camera = CameraSystem::get_active_camera(self.worlds.get_active_world())?;
+        Ok(camera.map_or(screen, |camera| camera.screen_to_world(screen)))
+    }
+
+    pub fn save_screenshot(&self, path: &Path) -> Result<()> {
+        self.render_frame()?.save_png(path)
+    }
+
+    fn draw_world(
+        registry: &Registry,
+        background_color: Color,
+        renderer: &mut dyn Renderer,
+    ) -> Result<()> {
+        // Clearing covers the whole window, the background only the virtual screen
+        renderer.clear(LETTERBOX_COLOR)?;
+        renderer.fill_rect(
+            Rect::new(0, 0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT),
+            background_color,
+        )?;
+        if registry.has_system::<RenderSystem>() {
+            RenderSystem::update(registry, renderer)?;
+        }
+        Ok(())
+    }
+}
+
+fn get_font_path() -> PathBuf {
+    Path::new(ASSETS_DIR).join(FONT_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_moving_positions(registry: &Registry) -> Result<Vec<(f64, f64)>> {
+        let mut positions = Vec::new();
+        for entity in registry.get_system_entities::<MovementSystem>()? {
+            positions.push(
+                registry
+                    .get_component::<TransformComponent>(entity)?
+                    .position,
+            );
+        }
+        positions.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(positions)
+    }
+
+    fn assert_positions_eq(found: &[(f64, f64)], expected: &[(f64, f64)]) {
+        assert_eq!(found.len(), expected.len());
+        for (found, expected) in found.iter().zip(expected) {
+            assert!(
+                (found.0 - expected.0).abs() < 1e-9,
+                "{:?} != {:?}",
+                found,
+                expected
+            );
+            assert!(
+                (found.1 - expected.1).abs() < 1e-9,
+                "{:?} != {:?}",
+                found,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn headless_frames_move_entities_by_their_velocity() -> Result<()> {
+        let mut game = Game::new_headless(ScriptedInput::new());
+        game.setup()?;
+        // Spawned entities join their systems at the end of the first frame
+        game.run_frames(1)?;
+        let start = get_moving_positions(game.worlds.get_active_world())?;
+        assert_eq!(start, vec![(100., 100.), (300., 100.), (500., 100.)]);
+
+        game.run_frames(30)?;
+        let offset = 40. * FIXED_DELTA_TIME * 30.;
+        let expected: Vec<(f64, f64)> = start.iter().map(|p| (p.0 + offset, p.1)).collect();
+        assert_positions_eq(
+            &get_moving_positions(game.worlds.get_active_world())?,
+            &expected,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn headless_run_stops_on_scripted_quit() -> Result<()> {
+        let input = ScriptedInput::new()
+            .with_key_press(2, DEBUG_DRAW_COLLIDERS_KEY)
+            .with_quit(10);
+        let mut game = Game::new_headless(input);
+        game.setup()?;
+        game.run_frames(100)?;
+        assert!(!game.is_running);
+        assert!(game.get_debug_draw_settings().colliders);
+
+        // The quit frame is still updated, the entities moved on frames 1 to 10
+        let offset = 40. * FIXED_DELTA_TIME * 10.;
+        assert_positions_eq(
+            &get_moving_positions(game.worlds.get_active_world())?,
+            &[
+                (100. + offset, 100.),
+                (300. + offset, 100.),
+                (500. + offset, 100.),
+            ],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rolled_back_frames_are_simulated_again_the_same_way() -> Result<()> {
+        let mut game = Game::new_headless(ScriptedInput::new());
+        game.setup()?;
+        game.run_frames(30)?;
+        let expected = game.worlds.get_active_world().snapshot()?;
+
+        game.rollback_to(10)?;
+        assert_eq!(game.get_frame(), 10);
+        assert_ne!(game.worlds.get_active_world().snapshot()?, expected);
+
+        game.run_frames(20)?;
+        assert_eq!(game.get_frame(), 30);
+        assert_eq!(game.worlds.get_active_world().snapshot()?, expected);
+
+        let err = game.rollback_to(31).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<EcsErrors>(),
+            Some(EcsErrors::RollbackFrameNotAvailable(31))
+        ));
+        Ok(())
+    }
+
+    fn get_level_entities(game: &Game) -> Vec<Entity> {
+        game.loaded_level
+            .as_ref()
+            .map(|loaded_level| loaded_level.entities.clone())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn failed_level_reloads_keep_the_loaded_level() -> Result<()> {
+        let mut game = Game::new_headless(ScriptedInput::new());
+        game.setup()?;
+        game.run_frames(1)?;
+        let entities = get_level_entities(&game);
+
+        // The first entity spawns, the second one fails. Level 1 has no ParticleSystem
+        let registry = game.worlds.get_active_world();
+        assert!(!registry.has_system::<ParticleSystem>());
+        let path = std::env::temp_dir().join(format!("broken_level_{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{ "systems": ["ParticleSystem"], "entities": [
+                { "name": "first", "components": { "TransformComponent": { "position": [0.0, 0.0] } } },
+                { "prefab": "missing" }
+            ] }"#,
+        )?;
+        let result = game.apply_level_file(&path);
+        std::fs::remove_file(&path)?;
+        assert!(result.is_err());
+        game.run_frames(1)?;
+
+        let registry = game.worlds.get_active_world();
+        assert_eq!(get_level_entities(&game), entities);
+        assert_eq!(registry.get_group_size(LEVEL_GROUP), entities.len());
+        assert_eq!(registry.find_by_name("first"), None);
+        assert!(registry.find_by_name("player").is_some());
+        assert!(!registry.has_system::<ParticleSystem>());
+        Ok(())
+    }
+
+    #[test]
+    fn prefab_reloads_replace_the_instances() -> Result<()> {
+        let mut game = Game::new_headless(ScriptedInput::new());
+        game.setup()?;
+        game.run_frames(1)?;
+        let entities = get_level_entities(&game);
 
-        self.canvas.present();
+        game.apply_prefab_file(&Path::new(ASSETS_DIR).join("prefabs/enemy.json"))?;
+        game.run_frames(1)?;
 
+        let registry = game.worlds.get_active_world();
+        let new_entities = get_level_entities(&game);
+        assert_eq!(new_entities.len(), entities.len());
+        assert_eq!(registry.get_group_size(LEVEL_GROUP), entities.len());
+        let respawned = entities
+            .iter()
+            .zip(&new_entities)
+            .filter(|(entity, new_entity)| entity != new_entity)
+            .count();
+        assert_eq!(respawned, 3);
+        assert_eq!(registry.get_group_size("enemies"), 3);
         Ok(())
     }
 }
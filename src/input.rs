@@ -0,0 +1,66 @@
+use sdl2::{
+    event::Event,
+    keyboard::{Keycode, Mod},
+};
+use std::collections::BTreeMap;
+
+/// Input for a headless game, events are fed to `Game::process_input` on the frame they
+/// are scheduled for
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedInput {
+    /// key => frame, value => events of that frame in order
+    events: BTreeMap<u64, Vec<Event>>,
+}
+
+impl ScriptedInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_event(mut self, frame: u64, event: Event) -> Self {
+        self.events.entry(frame).or_default().push(event);
+        self
+    }
+
+    /// Key is pressed on `frame` and released on the next one
+    pub fn with_key_press(self, frame: u64, keycode: Keycode) -> Self {
+        self.with_event(frame, key_event(keycode, true))
+            .with_event(frame + 1, key_event(keycode, false))
+    }
+
+    pub fn with_quit(self, frame: u64) -> Self {
+        self.with_event(frame, Event::Quit { timestamp: 0 })
+    }
+
+    /// Events stay in the script, so frames simulated again after a rollback get the
+    /// same input
+    pub fn get_events(&self, frame: u64) -> Vec<Event> {
+        self.events.get(&frame).cloned().unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+fn key_event(keycode: Keycode, is_down: bool) -> Event {
+    if is_down {
+        Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat: false,
+        }
+    } else {
+        Event::KeyUp {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat: false,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LevelErrors {
+    #[error("{file}:{line}:{column}: {message}")]
+    Syntax {
+        file: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("{file}:{line}: component {component}: {message}")]
+    InvalidComponent {
+        file: String,
+        line: usize,
+        component: String,
+        message: String,
+    },
+    #[error("{file}:{line}: {message}")]
+    InvalidEntity {
+        file: String,
+        line: usize,
+        message: String,
+    },
+    #[error("{file}:{line}: tile layer {layer}: {message}")]
+    InvalidLayer {
+        file: String,
+        line: usize,
+        layer: String,
+        message: String,
+    },
+    /// Errors about the whole file, e.g. a map setting that is not supported
+    #[error("{file}: {message}")]
+    InvalidMap { file: String, message: String },
+    #[error("{file}: system {system} does not exist")]
+    SystemDoesNotExist { file: String, system: String },
+}
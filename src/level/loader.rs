@@ -0,0 +1,242 @@
+use super::{
+    level_errors::LevelErrors,
+    tiled::{load_tiled_map_from_json, Tileset},
+};
+use crate::ecs::{
+    components::color_serde,
+    prefab::{component_factory_from_json, ComponentFactory, PrefabOverrides},
+    registry::{Entity, Registry},
+};
+use anyhow::{Context, Result};
+use sdl2::pixels::Color;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::{
+    any::TypeId,
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::RGB(30, 30, 30);
+pub const TILED_MAP_EXTENSION: &str = "tmj";
+
+/// A parsed level file, component values are validated against the registry when it is loaded
+pub struct Level {
+    pub path: PathBuf,
+    pub background_color: Color,
+    /// System type names, e.g. `MovementSystem`
+    pub systems: Vec<String>,
+    pub entities: Vec<LevelEntity>,
+    /// Only Tiled maps have tilesets
+    pub tilesets: Vec<Tileset>,
+    /// (width, height) in pixels, Tiled maps always have one
+    pub map_size: Option<(u32, u32)>,
+}
+
+pub struct LevelEntity {
+    /// Line of the entity in the level file, used in errors
+    pub line: usize,
+    pub name: Option<String>,
+    /// Components override the prefab's root components
+    pub prefab: Option<String>,
+    pub components: Vec<(TypeId, ComponentFactory)>,
+    pub groups: Vec<String>,
+}
+
+/// Level file layout, components are keyed by their registered name:
+/// `{ "background_color": [30, 30, 30, 255], "map_size": [800, 600],
+///    "systems": ["MovementSystem"],
+///    "entities": [{ "name": "player", "prefab": "enemy", "components": {}, "groups": [] }] }`
+#[derive(Deserialize)]
+struct LevelFile<'a> {
+    #[serde(with = "color_serde", default = "default_background_color")]
+    background_color: Color,
+    #[serde(default)]
+    map_size: Option<(u32, u32)>,
+    #[serde(default)]
+    systems: Vec<String>,
+    #[serde(borrow, default)]
+    entities: Vec<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+struct LevelEntityFile<'a> {
+    name: Option<String>,
+    prefab: Option<String>,
+    #[serde(borrow, default)]
+    components: BTreeMap<String, &'a RawValue>,
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+fn default_background_color() -> Color {
+    DEFAULT_BACKGROUND_COLOR
+}
+
+impl Level {
+    /// Spawns the level's entities and returns the root entities in file order
+    /// NOTE: If an entity fails to spawn the entities spawned before it are killed
+    pub fn spawn(&self, registry: &mut Registry) -> Result<Vec<Entity>> {
+        kill_on_error(registry, |registry| {
+            (0..self.entities.len())
+                .map(|index| self.spawn_entity(index, registry))
+                .collect()
+        })
+    }
+
+    /// Spawns `entities[index]` on its own, used to respawn prefab instances on hot reload
+    pub fn spawn_entity(&self, index: usize, registry: &mut Registry) -> Result<Entity> {
+        let level_entity = &self.entities[index];
+        let entity = kill_on_error(registry, |registry| {
+            spawn_level_entity(level_entity, registry)
+        })
+        .map_err(|err| LevelErrors::InvalidEntity {
+            file: self.path.display().to_string(),
+            line: level_entity.line,
+            message: err.to_string(),
+        })?;
+        Ok(entity)
+    }
+}
+
+/// Kills the entities `spawn` created if it fails, so nothing is left half spawned
+fn kill_on_error<T>(
+    registry: &mut Registry,
+    spawn: impl FnOnce(&mut Registry) -> Result<T>,
+) -> Result<T> {
+    let first_new = registry.get_pending_entities().len();
+    let result = spawn(registry);
+    if result.is_err() {
+        let new_entities = registry.get_pending_entities()[first_new..].to_vec();
+        for entity in new_entities {
+            registry.kill_entity(entity)?;
+        }
+    }
+    result
+}
+
+fn spawn_level_entity(level_entity: &LevelEntity, registry: &mut Registry) -> Result<Entity> {
+    let entity = match &level_entity.prefab {
+        Some(prefab) => {
+            let overrides = level_entity
+                .components
+                .iter()
+                .fold(PrefabOverrides::new(), |overrides, (type_id, factory)| {
+                    overrides.with_factory(*type_id, factory.clone())
+                });
+            registry.instantiate_prefab(prefab, &overrides)?
+        }
+        None => {
+            let entity = registry.create_entity();
+            for (type_id, factory) in &level_entity.components {
+                registry.add_component_with_id(entity, *type_id, factory()?)?;
+            }
+            entity
+        }
+    };
+
+    if let Some(name) = &level_entity.name {
+        registry.set_entity_name(entity, name)?;
+    }
+    for group in &level_entity.groups {
+        registry.add_entity_to_group(entity, group)?;
+    }
+    Ok(entity)
+}
+
+pub fn load_level_from_json(json: &str, path: &Path, registry: &Registry) -> Result<Level> {
+    let file = path.display().to_string();
+    let syntax_error = |err, line_offset| syntax_error(&file, err, line_offset);
+
+    let level_file: LevelFile = serde_json::from_str(json).map_err(|err| syntax_error(err, 0))?;
+
+    let mut entities = vec![];
+    for raw_entity in level_file.entities {
+        let line = get_line(json, raw_entity);
+        let entity_file: LevelEntityFile =
+            serde_json::from_str(raw_entity.get()).map_err(|err| syntax_error(err, line - 1))?;
+
+        let mut components = vec![];
+        for (component_name, raw_value) in entity_file.components {
+            let line = get_line(json, raw_value);
+            let value =
+                serde_json::from_str(raw_value.get()).map_err(|err| syntax_error(err, line - 1))?;
+            let component =
+                component_factory_from_json(registry, &component_name, value).map_err(|err| {
+                    LevelErrors::InvalidComponent {
+                        file: file.clone(),
+                        line,
+                        component: component_name.clone(),
+                        message: err.root_cause().to_string(),
+                    }
+                })?;
+            components.push(component);
+        }
+
+        entities.push(LevelEntity {
+            line,
+            name: entity_file.name,
+            prefab: entity_file.prefab,
+            components,
+            groups: entity_file.groups,
+        });
+    }
+
+    Ok(Level {
+        path: path.to_path_buf(),
+        background_color: level_file.background_color,
+        systems: level_file.systems,
+        entities,
+        tilesets: vec![],
+        map_size: level_file.map_size,
+    })
+}
+
+/// `.tmj` files are Tiled maps, everything else is a level file
+pub fn load_level_file(path: &Path, registry: &Registry) -> Result<Level> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("could not read level file {}", path.display()))?;
+    if path
+        .extension()
+        .is_some_and(|extension| extension == TILED_MAP_EXTENSION)
+    {
+        return load_tiled_map_from_json(&json, path, registry);
+    }
+    load_level_from_json(&json, path, registry)
+}
+
+/// `levels/level_{level}.tmj` inside the assets directory, or `level_{level}.json` when
+/// there is no Tiled map for the level
+pub fn get_level_path(assets_dir: &Path, level: i32) -> PathBuf {
+    let levels_dir = assets_dir.join("levels");
+    let tiled_map = levels_dir.join(format!("level_{}.{}", level, TILED_MAP_EXTENSION));
+    if tiled_map.exists() {
+        return tiled_map;
+    }
+    levels_dir.join(format!("level_{}.json", level))
+}
+
+pub(crate) fn syntax_error(file: &str, err: serde_json::Error, line_offset: usize) -> LevelErrors {
+    LevelErrors::Syntax {
+        file: file.to_string(),
+        line: err.line() + line_offset,
+        column: err.column(),
+        message: strip_position(&err),
+    }
+}
+
+/// 1 based line of a value borrowed from `json`
+pub(crate) fn get_line(json: &str, value: &RawValue) -> usize {
+    let offset = value.get().as_ptr() as usize - json.as_ptr() as usize;
+    json[..offset].matches('\n').count() + 1
+}
+
+/// serde_json puts the position relative to the parsed slice at the end of its messages
+fn strip_position(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    }
+}
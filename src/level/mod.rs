@@ -0,0 +1,4 @@
+pub mod level_errors;
+pub mod loader;
+pub mod tiled;
+pub mod watcher;
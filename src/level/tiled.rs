@@ -0,0 +1,667 @@
+//! Import of maps made with the Tiled editor, saved in its JSON format (`.tmj`).
+//! Tile layers become the layers of one entity with a TilemapComponent, objects become
+//! entities with a TransformComponent and the components listed in their custom properties.
+use super::{
+    level_errors::LevelErrors,
+    loader::{get_line, syntax_error, Level, LevelEntity, DEFAULT_BACKGROUND_COLOR},
+};
+use crate::ecs::{
+    components::{
+        RenderLayer, TileLayer, TilemapComponent, TilemapTileset, TransformComponent, TILE_SOLID,
+    },
+    prefab::{clone_factory, component_factory_from_json},
+    registry::Registry,
+};
+use anyhow::{Context, Result};
+use sdl2::pixels::Color;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::{
+    any::TypeId,
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Object property that instantiates a prefab, the other components override its root
+pub const PREFAB_PROPERTY: &str = "prefab";
+/// Map property with the comma separated names of the active systems
+pub const SYSTEMS_PROPERTY: &str = "systems";
+/// Boolean tile property, sets TILE_SOLID
+pub const SOLID_PROPERTY: &str = "solid";
+/// Name of the entity with the TilemapComponent
+pub const TILEMAP_ENTITY_NAME: &str = "tilemap";
+
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    pub first_gid: u32,
+    pub name: String,
+    /// Resolved relative to the map (or the external tileset file)
+    pub image: Option<PathBuf>,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub margin: u32,
+    pub spacing: u32,
+    /// key => local tile id, value => custom properties of the tile
+    pub tile_properties: BTreeMap<u32, BTreeMap<String, serde_json::Value>>,
+}
+
+impl Tileset {
+    /// The tileset image is registered as a texture under the tileset name
+    pub fn to_tilemap_tileset(&self) -> TilemapTileset {
+        TilemapTileset {
+            texture_id: self.name.clone(),
+            first_gid: self.first_gid,
+            tile_count: self.tile_count,
+            tile_width: self.tile_width,
+            tile_height: self.tile_height,
+            columns: self.columns,
+            margin: self.margin,
+            spacing: self.spacing,
+        }
+    }
+
+    /// key => global tile id, value => TILE_ flag bits, from the tile properties
+    pub fn get_tile_flags(&self) -> BTreeMap<u32, u32> {
+        let mut tile_flags = BTreeMap::new();
+        for (local_id, properties) in &self.tile_properties {
+            let mut flags = 0;
+            if properties.get(SOLID_PROPERTY) == Some(&serde_json::Value::Bool(true)) {
+                flags |= TILE_SOLID;
+            }
+            if flags != 0 {
+                tile_flags.insert(self.first_gid + local_id, flags);
+            }
+        }
+        tile_flags
+    }
+}
+
+#[derive(Deserialize)]
+struct TiledMap<'a> {
+    #[serde(default)]
+    infinite: bool,
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    backgroundcolor: Option<String>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+    /// Parsed one by one so errors can name the line of the layer
+    #[serde(borrow, default)]
+    layers: Vec<&'a RawValue>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Deserialize)]
+struct TiledLayer<'a> {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    layer_type: String,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    #[serde(default)]
+    offsetx: f64,
+    #[serde(default)]
+    offsety: f64,
+    #[serde(default = "default_visible")]
+    visible: bool,
+    encoding: Option<String>,
+    data: Option<Vec<u32>>,
+    #[serde(borrow, default)]
+    objects: Vec<&'a RawValue>,
+    #[serde(borrow, default)]
+    layers: Vec<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default)]
+    name: String,
+    /// Renamed to class in Tiled 1.9 and back to type in 1.10
+    #[serde(rename = "type", alias = "class", default)]
+    object_type: String,
+    #[serde(default)]
+    x: f64,
+    #[serde(default)]
+    y: f64,
+    #[serde(default)]
+    height: f64,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    columns: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    tiles: Vec<TiledTile>,
+}
+
+#[derive(Deserialize)]
+struct TiledTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+impl TiledTileset {
+    /// `dir` is the directory of the file the tileset was read from
+    fn into_tileset(self, first_gid: u32, dir: &Path) -> Tileset {
+        Tileset {
+            first_gid,
+            name: self.name,
+            image: self.image.map(|image| dir.join(image)),
+            image_width: self.imagewidth,
+            image_height: self.imageheight,
+            tile_width: self.tilewidth,
+            tile_height: self.tileheight,
+            columns: self.columns,
+            tile_count: self.tilecount,
+            margin: self.margin,
+            spacing: self.spacing,
+            tile_properties: self
+                .tiles
+                .into_iter()
+                .map(|tile| {
+                    let properties = tile
+                        .properties
+                        .into_iter()
+                        .map(|property| (property.name, property.value))
+                        .collect();
+                    (tile.id, properties)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Converts a Tiled map into a level, errors name the map file and the line of the
+/// offending layer or object
+pub fn load_tiled_map_from_json(json: &str, path: &Path, registry: &Registry) -> Result<Level> {
+    let file = path.display().to_string();
+    let map: TiledMap = serde_json::from_str(json).map_err(|err| syntax_error(&file, err, 0))?;
+    if map.infinite {
+        return Err(LevelErrors::InvalidMap {
+            file,
+            message: "infinite maps are not supported".to_string(),
+        }
+        .into());
+    }
+
+    let map_dir = path.parent().unwrap_or(Path::new(""));
+    let mut tilesets = vec![];
+    for tileset in map.tilesets {
+        tilesets.push(load_tileset(tileset, map_dir)?);
+    }
+    tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+    let mut background_color = DEFAULT_BACKGROUND_COLOR;
+    if let Some(color) = &map.backgroundcolor {
+        background_color = parse_color(color).ok_or_else(|| LevelErrors::InvalidMap {
+            file: file.clone(),
+            message: format!("invalid background color {}", color),
+        })?;
+    }
+
+    let mut systems = vec![];
+    for property in &map.properties {
+        if property.name == SYSTEMS_PROPERTY {
+            let names = property.value.as_str().unwrap_or_default();
+            systems.extend(
+                names
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from),
+            );
+        }
+    }
+
+    let mut importer = TiledImporter {
+        json,
+        file,
+        registry,
+        map_size: (map.width, map.height),
+        tile_size: (map.tilewidth, map.tileheight),
+        entities: vec![],
+        tile_layers: vec![],
+        tilemap_index: None,
+        tilemap_line: 0,
+    };
+    for layer in &map.layers {
+        importer.import_layer(layer, (0.0, 0.0))?;
+    }
+
+    Ok(Level {
+        path: path.to_path_buf(),
+        background_color,
+        systems,
+        entities: importer.finish(&tilesets),
+        tilesets,
+        map_size: Some((map.width * map.tilewidth, map.height * map.tileheight)),
+    })
+}
+
+pub fn load_tiled_map_file(path: &Path, registry: &Registry) -> Result<Level> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("could not read Tiled map {}", path.display()))?;
+    load_tiled_map_from_json(&json, path, registry)
+}
+
+/// External tilesets (`.tsj`) are loaded relative to the map
+fn load_tileset(tileset: TiledTileset, map_dir: &Path) -> Result<Tileset> {
+    let first_gid = tileset.firstgid;
+    let Some(source) = &tileset.source else {
+        return Ok(tileset.into_tileset(first_gid, map_dir));
+    };
+
+    let path = map_dir.join(source);
+    let file = path.display().to_string();
+    let json = fs::read_to_string(&path)
+        .with_context(|| format!("could not read Tiled tileset {}", file))?;
+    let external: TiledTileset =
+        serde_json::from_str(&json).map_err(|err| syntax_error(&file, err, 0))?;
+    Ok(external.into_tileset(first_gid, path.parent().unwrap_or(Path::new(""))))
+}
+
+/// `#rrggbb` or `#aarrggbb`
+fn parse_color(color: &str) -> Option<Color> {
+    let hex = color.strip_prefix('#')?;
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    let [a, r, g, b] = value.to_be_bytes();
+    match hex.len() {
+        6 => Some(Color::RGB(r, g, b)),
+        8 => Some(Color::RGBA(r, g, b, a)),
+        _ => None,
+    }
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+struct TiledImporter<'a> {
+    json: &'a str,
+    file: String,
+    registry: &'a Registry,
+    map_size: (u32, u32),
+    tile_size: (u32, u32),
+    entities: Vec<LevelEntity>,
+    tile_layers: Vec<TileLayer>,
+    /// The tilemap entity goes where the first tile layer was, so the layer order of the
+    /// map is kept
+    tilemap_index: Option<usize>,
+    /// Line of the first tile layer, errors about the tilemap entity point there
+    tilemap_line: usize,
+}
+
+impl TiledImporter<'_> {
+    fn import_layer(&mut self, raw_layer: &RawValue, offset: (f64, f64)) -> Result<()> {
+        let line = get_line(self.json, raw_layer);
+        let layer: TiledLayer = serde_json::from_str(raw_layer.get())
+            .map_err(|err| syntax_error(&self.file, err, line - 1))?;
+        let offset = (offset.0 + layer.offsetx, offset.1 + layer.offsety);
+        match layer.layer_type.as_str() {
+            "tilelayer" => self.import_tile_layer(&layer, line, offset),
+            "objectgroup" => {
+                for object in &layer.objects {
+                    self.import_object(object, offset)?;
+                }
+                Ok(())
+            }
+            "group" => {
+                for child in &layer.layers {
+                    self.import_layer(child, offset)?;
+                }
+                Ok(())
+            }
+            // Image layers are not supported yet
+            _ => Ok(()),
+        }
+    }
+
+    fn import_tile_layer(
+        &mut self,
+        layer: &TiledLayer,
+        line: usize,
+        offset: (f64, f64),
+    ) -> Result<()> {
+        let invalid_layer = |message: String| LevelErrors::InvalidLayer {
+            file: self.file.clone(),
+            line,
+            layer: layer.name.clone(),
+            message,
+        };
+        if let Some(encoding) = layer
+            .encoding
+            .as_deref()
+            .filter(|encoding| *encoding != "csv")
+        {
+            return Err(invalid_layer(format!(
+                "{} encoding is not supported, save the map with CSV layer data",
+                encoding
+            ))
+            .into());
+        }
+        // Layers of finite maps always have the size of the map
+        if (layer.width, layer.height) != self.map_size {
+            return Err(invalid_layer(format!(
+                "layer is {}x{} tiles but the map is {}x{}",
+                layer.width, layer.height, self.map_size.0, self.map_size.1
+            ))
+            .into());
+        }
+        let tiles = layer.data.clone().unwrap_or_default();
+        let tile_count = (self.map_size.0 * self.map_size.1) as usize;
+        if tiles.len() != tile_count {
+            return Err(invalid_layer(format!(
+                "expected {} tiles, found {}",
+                tile_count,
+                tiles.len()
+            ))
+            .into());
+        }
+
+        if self.tilemap_index.is_none() {
+            self.tilemap_index = Some(self.entities.len());
+            self.tilemap_line = line;
+        }
+        self.tile_layers.push(TileLayer {
+            name: layer.name.clone(),
+            tiles,
+            offset,
+            is_visible: layer.visible,
+        });
+        Ok(())
+    }
+
+    fn finish(mut self, tilesets: &[Tileset]) -> Vec<LevelEntity> {
+        let Some(index) = self.tilemap_index else {
+            return self.entities;
+        };
+        let tilemap = TilemapComponent {
+            width: self.map_size.0,
+            height: self.map_size.1,
+            tile_width: self.tile_size.0,
+            tile_height: self.tile_size.1,
+            layers: self.tile_layers,
+            tilesets: tilesets.iter().map(Tileset::to_tilemap_tileset).collect(),
+            tile_flags: tilesets.iter().flat_map(Tileset::get_tile_flags).collect(),
+            layer: RenderLayer::Background,
+            z_index: 0,
+        };
+        let transform = TransformComponent {
+            position: (0.0, 0.0),
+        };
+        self.entities.insert(
+            index,
+            LevelEntity {
+                line: self.tilemap_line,
+                name: Some(TILEMAP_ENTITY_NAME.to_string()),
+                prefab: None,
+                components: vec![
+                    (TypeId::of::<TransformComponent>(), clone_factory(transform)),
+                    (TypeId::of::<TilemapComponent>(), clone_factory(tilemap)),
+                ],
+                groups: vec![],
+            },
+        );
+        self.entities
+    }
+
+    /// Custom properties named after a registered component hold its value, either as a
+    /// JSON string or as a Tiled custom class with the same fields
+    fn import_object(&mut self, raw_object: &RawValue, offset: (f64, f64)) -> Result<()> {
+        let line = get_line(self.json, raw_object);
+        let object: TiledObject = serde_json::from_str(raw_object.get())
+            .map_err(|err| syntax_error(&self.file, err, line - 1))?;
+
+        // Tile objects are anchored at their bottom left corner
+        let y = match object.gid {
+            Some(_) => object.y - object.height,
+            None => object.y,
+        };
+        let transform = TransformComponent {
+            position: (offset.0 + object.x, offset.1 + y),
+        };
+
+        let mut prefab = None;
+        let mut components = vec![(TypeId::of::<TransformComponent>(), clone_factory(transform))];
+        for property in object.properties {
+            if property.name == PREFAB_PROPERTY {
+                prefab = property.value.as_str().map(String::from);
+                continue;
+            }
+            let value = match property.value {
+                serde_json::Value::String(text) => serde_json::from_str(&text)
+                    .map_err(|err| self.invalid_component(line, &property.name, &err.into()))?,
+                value => value,
+            };
+            let component = component_factory_from_json(self.registry, &property.name, value)
+                .map_err(|err| self.invalid_component(line, &property.name, &err))?;
+            components.retain(|(type_id, _)| *type_id != component.0);
+            components.push(component);
+        }
+
+        self.entities.push(LevelEntity {
+            line,
+            name: Some(object.name).filter(|name| !name.is_empty()),
+            prefab,
+            components,
+            groups: Some(object.object_type)
+                .filter(|object_type| !object_type.is_empty())
+                .into_iter()
+                .collect(),
+        });
+        Ok(())
+    }
+
+    fn invalid_component(&self, line: usize, component: &str, err: &anyhow::Error) -> LevelErrors {
+        LevelErrors::InvalidComponent {
+            file: self.file.clone(),
+            line,
+            component: component.to_string(),
+            message: err.root_cause().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::components::{NameComponent, VelocityComponent, FLIPPED_HORIZONTALLY_FLAG};
+
+    const MAP: &str = r##"{
+  "infinite": false,
+  "width": 2,
+  "height": 2,
+  "tilewidth": 16,
+  "tileheight": 16,
+  "backgroundcolor": "#102030",
+  "properties": [
+    { "name": "systems", "type": "string", "value": "MovementSystem, RenderSystem" }
+  ],
+  "tilesets": [
+    {
+      "firstgid": 1, "name": "terrain", "image": "terrain.png",
+      "imagewidth": 32, "imageheight": 32, "tilewidth": 16, "tileheight": 16,
+      "columns": 2, "tilecount": 4,
+      "tiles": [{ "id": 1, "properties": [{ "name": "solid", "type": "bool", "value": true }] }]
+    }
+  ],
+  "layers": [
+    { "name": "ground", "type": "tilelayer", "width": 2, "height": 2, "data": [1, 2, 2147483650, 0] },
+    {
+      "name": "objects", "type": "objectgroup", "offsetx": 4, "offsety": 0,
+      "objects": [
+        {
+          "name": "player", "type": "players", "x": 8, "y": 16,
+          "properties": [{ "name": "VelocityComponent", "type": "string", "value": "[1.5, 0]" }]
+        },
+        { "name": "crate", "x": 16, "y": 32, "height": 16, "gid": 3 }
+      ]
+    }
+  ]
+}"##;
+
+    fn create_registry() -> Result<Registry> {
+        let mut registry = Registry::default();
+        registry.register_component::<TransformComponent>()?;
+        registry.register_component::<VelocityComponent>()?;
+        registry.register_component::<TilemapComponent>()?;
+        registry.register_component::<NameComponent>()?;
+        registry.register_component_serializer::<TransformComponent>()?;
+        registry.register_component_serializer::<VelocityComponent>()?;
+        registry.register_component_serializer::<TilemapComponent>()?;
+        Ok(registry)
+    }
+
+    fn get_position(registry: &Registry, name: &str) -> Result<(f64, f64)> {
+        let entity = registry
+            .find_by_name(name)
+            .context("entity is not spawned")?;
+        Ok(registry
+            .get_component::<TransformComponent>(entity)?
+            .position)
+    }
+
+    fn load_error(json: &str) -> Result<String> {
+        let registry = create_registry()?;
+        match load_tiled_map_from_json(json, Path::new("maps/map.tmj"), &registry) {
+            Ok(_) => anyhow::bail!("map is loaded"),
+            Err(err) => Ok(err.to_string()),
+        }
+    }
+
+    #[test]
+    fn tiled_maps_are_imported_as_levels() -> Result<()> {
+        let mut registry = create_registry()?;
+        let level = load_tiled_map_from_json(MAP, Path::new("maps/map.tmj"), &registry)?;
+        assert_eq!(level.background_color, Color::RGB(16, 32, 48));
+        assert_eq!(level.systems, ["MovementSystem", "RenderSystem"]);
+        assert_eq!(level.map_size, Some((32, 32)));
+        assert_eq!(
+            level.tilesets[0].image.as_deref(),
+            Some(Path::new("maps/terrain.png"))
+        );
+
+        // The tilemap entity is where the first tile layer is
+        assert_eq!(level.entities[0].line, 20);
+        assert_eq!(level.entities[1].line, 24);
+
+        let entities = level.spawn(&mut registry)?;
+        assert_eq!(entities.len(), 3);
+        assert_eq!(
+            registry.find_by_name(TILEMAP_ENTITY_NAME),
+            Some(entities[0])
+        );
+
+        let tilemap = registry.get_component::<TilemapComponent>(entities[0])?;
+        assert_eq!((tilemap.width, tilemap.height), (2, 2));
+        assert_eq!(tilemap.layers.len(), 1);
+        assert_eq!(
+            tilemap.layers[0].tiles,
+            [1, 2, 2 | FLIPPED_HORIZONTALLY_FLAG, 0]
+        );
+        assert_eq!(tilemap.tile_flags, BTreeMap::from([(2, TILE_SOLID)]));
+        assert_eq!(tilemap.tilesets[0].texture_id, "terrain");
+        assert_eq!(tilemap.layer, RenderLayer::Background);
+        drop(tilemap);
+
+        // Objects are moved by the layer offset
+        let player = registry
+            .find_by_name("player")
+            .context("player is not spawned")?;
+        assert_eq!(get_position(&registry, "player")?, (12., 16.));
+        assert_eq!(registry.get_component::<VelocityComponent>(player)?.0, 1.5);
+        assert!(registry.entity_belongs_to_group(player, "players"));
+        // Tile objects are anchored at their bottom left corner
+        assert_eq!(get_position(&registry, "crate")?, (20., 16.));
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_maps_are_rejected() -> Result<()> {
+        let infinite = MAP.replace(r#""infinite": false"#, r#""infinite": true"#);
+        assert_eq!(
+            load_error(&infinite)?,
+            "maps/map.tmj: infinite maps are not supported"
+        );
+
+        let base64 = MAP.replace(
+            r#""width": 2, "height": 2, "data""#,
+            r#""width": 2, "height": 2, "encoding": "base64", "data""#,
+        );
+        assert_eq!(
+            load_error(&base64)?,
+            "maps/map.tmj:20: tile layer ground: base64 encoding is not supported, save the \
+             map with CSV layer data"
+        );
+
+        let wrong_size = MAP.replace(
+            r#""width": 2, "height": 2, "data": [1, 2, 2147483650, 0]"#,
+            r#""width": 4, "height": 1, "data": [1, 2, 2147483650, 0]"#,
+        );
+        assert_eq!(
+            load_error(&wrong_size)?,
+            "maps/map.tmj:20: tile layer ground: layer is 4x1 tiles but the map is 2x2"
+        );
+
+        let missing_tiles = MAP.replace("[1, 2, 2147483650, 0]", "[1, 2]");
+        assert_eq!(
+            load_error(&missing_tiles)?,
+            "maps/map.tmj:20: tile layer ground: expected 4 tiles, found 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_object_components_name_the_object_line() -> Result<()> {
+        let json = MAP.replace(r#""value": "[1.5, 0]""#, r#""value": "[1.5]""#);
+        let err = load_error(&json)?;
+        assert!(
+            err.starts_with("maps/map.tmj:24: component VelocityComponent:"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+}
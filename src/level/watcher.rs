@@ -0,0 +1,69 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Watches files by polling their modification times, used to hot reload level and
+/// prefab files while the game is running
+pub struct AssetWatcher {
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+    /// key => watched file, value => modification time seen at the last poll
+    files: BTreeMap<PathBuf, Option<SystemTime>>,
+}
+
+impl AssetWatcher {
+    pub fn new(poll_interval: Duration) -> Self {
+        Self {
+            poll_interval,
+            last_poll: None,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// NOTE: Watching a file again resets its modification time
+    pub fn watch(&mut self, path: &Path) {
+        self.files
+            .insert(path.to_path_buf(), get_modified_time(path));
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    pub fn is_watching(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Returns the files modified since the last poll. Files are checked at most once per
+    /// poll interval, in between nothing is returned.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|last_poll| now.duration_since(last_poll) < self.poll_interval)
+        {
+            return vec![];
+        }
+        self.last_poll = Some(now);
+
+        let mut changed_files = vec![];
+        for (path, modified_time) in self.files.iter_mut() {
+            let current_modified_time = get_modified_time(path);
+            // A missing file is usually an editor in the middle of saving it
+            if current_modified_time.is_some() && current_modified_time != *modified_time {
+                *modified_time = current_modified_time;
+                changed_files.push(path.clone());
+            }
+        }
+        changed_files
+    }
+}
+
+fn get_modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
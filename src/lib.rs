@@ -0,0 +1,8 @@
+pub mod debug_overlay;
+pub mod ecs;
+pub mod game;
+pub mod input;
+pub mod level;
+pub mod logger;
+pub mod renderer;
+pub mod window;
@@ -13,13 +13,16 @@ pub enum LogLevel {
 pub struct LogEntry {
     pub level: LogLevel,
     pub message: String,
+    /// The message without the level prefix and the date
+    pub text: String,
 }
 
 impl LogEntry {
-    fn new(level: LogLevel, message: &str) -> Self {
+    fn new(level: LogLevel, message: &str, text: &str) -> Self {
         Self {
             level,
             message: message.to_string(),
+            text: text.to_string(),
         }
     }
 }
@@ -69,8 +72,9 @@ impl Logger {
         };
 
         let date = Utc::now().to_string();
-        let message = format!("{} [{}]: {}", prefix, date, message);
-        let entry = LogEntry::new(level, &message);
+        let text = message;
+        let message = format!("{} [{}]: {}", prefix, date, text);
+        let entry = LogEntry::new(level, &message, text);
         self.log_entries.push(entry);
         println!("{}", message);
     }
@@ -1,9 +1,5 @@
-use crate::game::Game;
 use anyhow::Result;
-
-mod ecs;
-mod game;
-mod logger;
+use engine::game::Game;
 
 fn main() -> Result<()> {
     let mut game = Game::new("Demo")?;
@@ -0,0 +1,42 @@
+use anyhow::Result;
+use sdl2::{pixels::Color, rect::Rect};
+
+pub mod asset_store;
+pub mod recording_renderer;
+pub mod renderer_errors;
+pub mod screenshot;
+pub mod shapes;
+pub mod sdl_renderer;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct TextureFlip {
+    pub horizontal: bool,
+    pub vertical: bool,
+}
+
+/// Everything the game draws goes through a renderer so the same systems can draw to a
+/// window, an offscreen surface or a draw call recording in tests
+pub trait Renderer {
+    fn clear(&mut self, color: Color) -> Result<()>;
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<()>;
+
+    /// Outline of the rectangle, one pixel wide
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<()>;
+
+    fn draw_line(&mut self, start: (i32, i32), end: (i32, i32), color: Color) -> Result<()>;
+
+    /// `src` is the part of the texture to draw, None draws the whole texture
+    fn draw_texture(
+        &mut self,
+        texture_id: &str,
+        src: Option<Rect>,
+        dst: Rect,
+        flip: TextureFlip,
+    ) -> Result<()>;
+
+    /// `position` is the top left corner of the text
+    fn draw_text(&mut self, text: &str, position: (i32, i32), color: Color) -> Result<()>;
+
+    fn present(&mut self) -> Result<()>;
+}
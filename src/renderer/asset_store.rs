@@ -0,0 +1,205 @@
+use super::renderer_errors::RendererErrors;
+use anyhow::{Context, Result};
+use sdl2::{
+    image::LoadTexture,
+    render::{Texture, TextureCreator},
+    ttf::{Font, Sdl2TtfContext},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+pub const IMAGE_EXTENSION: &str = "png";
+
+/// SDL_ttf can only be initialized once, fonts borrow the context so it lives for the
+/// whole program
+static TTF_CONTEXT: OnceLock<Sdl2TtfContext> = OnceLock::new();
+
+fn get_ttf_context() -> Result<&'static Sdl2TtfContext> {
+    if let Some(ttf_context) = TTF_CONTEXT.get() {
+        return Ok(ttf_context);
+    }
+    let ttf_context = sdl2::ttf::init()?;
+    Ok(TTF_CONTEXT.get_or_init(|| ttf_context))
+}
+
+/// Texture ids and the image files behind them. Images are loaded through sdl2::image the
+/// first time they are needed and cached, a texture belongs to the renderer that created it
+/// so every SdlRenderer has its own store.
+/// The store also has the font text is drawn with, loaded through sdl2::ttf.
+/// NOTE: With unsafe_textures dropping a texture does not free it, the store destroys its
+/// textures when they are replaced and when it is dropped, so the renderer that created
+/// them must outlive the store
+#[derive(Default)]
+pub struct AssetStore {
+    texture_paths: BTreeMap<String, PathBuf>,
+    textures: HashMap<String, Texture>,
+    font_path: Option<(PathBuf, u16)>,
+    font: Option<Font<'static, 'static>>,
+}
+
+impl AssetStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_texture_paths(texture_paths: BTreeMap<String, PathBuf>) -> Self {
+        let mut asset_store = Self::default();
+        asset_store.texture_paths = texture_paths;
+        asset_store
+    }
+
+    pub fn with_font_path(mut self, path: &Path, point_size: u16) -> Self {
+        self.set_font_path(path, point_size);
+        self
+    }
+
+    /// NOTE: If the texture id is added again with another path the cached texture is destroyed
+    pub fn add_texture_path(&mut self, texture_id: &str, path: &Path) {
+        let previous_path = self
+            .texture_paths
+            .insert(texture_id.to_string(), path.to_path_buf());
+        if previous_path.is_some_and(|previous_path| previous_path != path) {
+            if let Some(texture) = self.textures.remove(texture_id) {
+                destroy_texture(texture);
+            }
+        }
+    }
+
+    pub fn get_texture_paths(&self) -> &BTreeMap<String, PathBuf> {
+        &self.texture_paths
+    }
+
+    pub fn get_texture_path(&self, texture_id: &str) -> Option<&Path> {
+        self.texture_paths.get(texture_id).map(PathBuf::as_path)
+    }
+
+    pub fn is_texture_loaded(&self, texture_id: &str) -> bool {
+        self.textures.contains_key(texture_id)
+    }
+
+    /// NOTE: If you add the same texture id again it will override, the old texture is destroyed
+    pub fn add_texture(&mut self, texture_id: &str, texture: Texture) {
+        if let Some(previous_texture) = self.textures.insert(texture_id.to_string(), texture) {
+            destroy_texture(previous_texture);
+        }
+    }
+
+    /// Loads the texture unless it is cached already
+    pub fn load_texture<C>(
+        &mut self,
+        texture_creator: &TextureCreator<C>,
+        texture_id: &str,
+    ) -> Result<&Texture> {
+        if !self.textures.contains_key(texture_id) {
+            let path = self
+                .texture_paths
+                .get(texture_id)
+                .ok_or_else(|| RendererErrors::TextureDoesNotExist(texture_id.to_string()))?;
+            if fs::metadata(path).is_err() {
+                return Err(RendererErrors::AssetDoesNotExist {
+                    asset: texture_id.to_string(),
+                    path: path.display().to_string(),
+                }
+                .into());
+            }
+            let texture = texture_creator.load_texture(path).map_err(|message| {
+                RendererErrors::AssetLoadFailed {
+                    asset: texture_id.to_string(),
+                    path: path.display().to_string(),
+                    message,
+                }
+            })?;
+            self.textures.insert(texture_id.to_string(), texture);
+        }
+        Ok(&self.textures[texture_id])
+    }
+
+    /// NOTE: The cached font is dropped, the new one is loaded when text is drawn next
+    pub fn set_font_path(&mut self, path: &Path, point_size: u16) {
+        self.font_path = Some((path.to_path_buf(), point_size));
+        self.font = None;
+    }
+
+    pub fn get_font_path(&self) -> Option<(&Path, u16)> {
+        self.font_path
+            .as_ref()
+            .map(|(path, point_size)| (path.as_path(), *point_size))
+    }
+
+    /// Loads the font unless it is cached already
+    pub fn load_font(&mut self) -> Result<&Font<'static, 'static>> {
+        if self.font.is_none() {
+            let (path, point_size) = self
+                .font_path
+                .as_ref()
+                .ok_or(RendererErrors::FontDoesNotExist)?;
+            if fs::metadata(path).is_err() {
+                return Err(RendererErrors::AssetDoesNotExist {
+                    asset: "font".to_string(),
+                    path: path.display().to_string(),
+                }
+                .into());
+            }
+            let font = get_ttf_context()?
+                .load_font(path, *point_size)
+                .map_err(|message| RendererErrors::AssetLoadFailed {
+                    asset: "font".to_string(),
+                    path: path.display().to_string(),
+                    message,
+                })?;
+            self.font = Some(font);
+        }
+        Ok(self.font.as_ref().expect("font is loaded above"))
+    }
+
+    /// Loads every texture up front so missing files are reported before the first frame
+    pub fn load_all_textures<C>(&mut self, texture_creator: &TextureCreator<C>) -> Result<()> {
+        let texture_ids: Vec<String> = self.texture_paths.keys().cloned().collect();
+        for texture_id in texture_ids {
+            self.load_texture(texture_creator, &texture_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AssetStore {
+    fn drop(&mut self) {
+        for (_, texture) in self.textures.drain() {
+            destroy_texture(texture);
+        }
+    }
+}
+
+fn destroy_texture(texture: Texture) {
+    // SAFETY: Textures in the store belong to the renderer that owns the store, which
+    // outlives it
+    unsafe { texture.destroy() };
+}
+
+/// Every `.png` file in `dir` as (texture id, path), the texture id is the file name
+/// without the extension
+pub fn get_image_paths(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("could not read image directory {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == IMAGE_EXTENSION)
+        {
+            let texture_id = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            paths.push((texture_id, path));
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
@@ -0,0 +1,110 @@
+use super::{Renderer, TextureFlip};
+use anyhow::Result;
+use sdl2::{pixels::Color, rect::Rect};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCall {
+    Clear(Color),
+    FillRect {
+        rect: Rect,
+        color: Color,
+    },
+    DrawRect {
+        rect: Rect,
+        color: Color,
+    },
+    DrawLine {
+        start: (i32, i32),
+        end: (i32, i32),
+        color: Color,
+    },
+    DrawTexture {
+        texture_id: String,
+        src: Option<Rect>,
+        dst: Rect,
+        flip: TextureFlip,
+    },
+    DrawText {
+        text: String,
+        position: (i32, i32),
+        color: Color,
+    },
+    Present,
+}
+
+/// Records draw calls instead of drawing, tests assert on the recorded list
+#[derive(Debug, Default)]
+pub struct RecordingRenderer {
+    draw_calls: Vec<DrawCall>,
+}
+
+impl RecordingRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_draw_calls(&self) -> &[DrawCall] {
+        &self.draw_calls
+    }
+
+    pub fn take_draw_calls(&mut self) -> Vec<DrawCall> {
+        std::mem::take(&mut self.draw_calls)
+    }
+
+    pub fn clear_draw_calls(&mut self) {
+        self.draw_calls.clear();
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn clear(&mut self, color: Color) -> Result<()> {
+        self.draw_calls.push(DrawCall::Clear(color));
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<()> {
+        self.draw_calls.push(DrawCall::FillRect { rect, color });
+        Ok(())
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<()> {
+        self.draw_calls.push(DrawCall::DrawRect { rect, color });
+        Ok(())
+    }
+
+    fn draw_line(&mut self, start: (i32, i32), end: (i32, i32), color: Color) -> Result<()> {
+        self.draw_calls
+            .push(DrawCall::DrawLine { start, end, color });
+        Ok(())
+    }
+
+    fn draw_texture(
+        &mut self,
+        texture_id: &str,
+        src: Option<Rect>,
+        dst: Rect,
+        flip: TextureFlip,
+    ) -> Result<()> {
+        self.draw_calls.push(DrawCall::DrawTexture {
+            texture_id: texture_id.to_string(),
+            src,
+            dst,
+            flip,
+        });
+        Ok(())
+    }
+
+    fn draw_text(&mut self, text: &str, position: (i32, i32), color: Color) -> Result<()> {
+        self.draw_calls.push(DrawCall::DrawText {
+            text: text.to_string(),
+            position,
+            color,
+        });
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<()> {
+        self.draw_calls.push(DrawCall::Present);
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RendererErrors {
+    #[error("Texture {0} is not loaded")]
+    TextureDoesNotExist(String),
+    #[error("Asset {asset} does not exist at {path}")]
+    AssetDoesNotExist { asset: String, path: String },
+    #[error("Could not load asset {asset} from {path}: {message}")]
+    AssetLoadFailed {
+        asset: String,
+        path: String,
+        message: String,
+    },
+    #[error("No font is loaded, text can not be drawn")]
+    FontDoesNotExist,
+    #[error("Frame is {found:?} but {expected:?} was expected")]
+    FrameSizeMismatch {
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    #[error("Golden image {golden} does not exist, set {env}=1 to record it")]
+    GoldenImageDoesNotExist { golden: String, env: &'static str },
+    #[error("Frame does not match {golden}, {differing_pixels} pixels differ by up to {max_difference}. The frame is saved to {actual}")]
+    GoldenImageMismatch {
+        golden: String,
+        actual: String,
+        differing_pixels: usize,
+        max_difference: u8,
+    },
+    #[error("Image format is not supported: {0}")]
+    UnsupportedImageFormat(String),
+}
@@ -0,0 +1,266 @@
+use super::renderer_errors::RendererErrors;
+use anyhow::{Context, Error, Result};
+use sdl2::{pixels::PixelFormatEnum, surface::Surface};
+use std::{
+    env,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// Set to anything but 0 to write the golden images from the frames instead of comparing
+/// them, e.g. `UPDATE_GOLDEN=1 cargo test`
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// A rendered frame as tightly packed RGBA8 pixels, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Result of comparing two frames of the same size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameDiff {
+    pub differing_pixels: usize,
+    /// Largest difference of a single channel
+    pub max_difference: u8,
+}
+
+impl Frame {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn from_surface(surface: &Surface) -> Result<Self> {
+        let surface = surface
+            .convert_format(PixelFormatEnum::RGBA32)
+            .map_err(Error::msg)?;
+        let (width, height) = surface.size();
+        let pitch = surface.pitch() as usize;
+        let row_length = width as usize * 4;
+
+        let mut frame = Self::new(width, height);
+        surface.with_lock(|pixels| {
+            for (row, frame_row) in frame.pixels.chunks_exact_mut(row_length).enumerate() {
+                frame_row.copy_from_slice(&pixels[row * pitch..row * pitch + row_length]);
+            }
+        });
+        Ok(frame)
+    }
+
+    /// Returns [r, g, b, a]
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = ((y * self.width + x) * 4) as usize;
+        self.pixels[index..index + 4].try_into().ok()
+    }
+
+    pub fn compare(&self, other: &Frame) -> Result<FrameDiff> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(RendererErrors::FrameSizeMismatch {
+                expected: (other.width, other.height),
+                found: (self.width, self.height),
+            }
+            .into());
+        }
+
+        let mut diff = FrameDiff {
+            differing_pixels: 0,
+            max_difference: 0,
+        };
+        for (pixel, other_pixel) in self
+            .pixels
+            .chunks_exact(4)
+            .zip(other.pixels.chunks_exact(4))
+        {
+            let difference = pixel
+                .iter()
+                .zip(other_pixel)
+                .map(|(channel, other_channel)| channel.abs_diff(*other_channel))
+                .max()
+                .unwrap_or(0);
+            if difference > 0 {
+                diff.differing_pixels += 1;
+                diff.max_difference = diff.max_difference.max(difference);
+            }
+        }
+        Ok(diff)
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("could not create PNG file {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()?
+            .write_image_data(&self.pixels)
+            .with_context(|| format!("could not write PNG file {}", path.display()))
+    }
+
+    /// Palette and grayscale images are expanded, 16 bit images are not supported
+    pub fn load_png(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("could not open PNG file {}", path.display()))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+        let buffer = &buffer[..info.buffer_size()];
+
+        let pixels = match (info.color_type, info.bit_depth) {
+            (png::ColorType::Rgba, png::BitDepth::Eight) => buffer.to_vec(),
+            (png::ColorType::Rgb, png::BitDepth::Eight) => buffer
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            (png::ColorType::Grayscale, png::BitDepth::Eight) => {
+                buffer.iter().flat_map(|&v| [v, v, v, 255]).collect()
+            }
+            (png::ColorType::GrayscaleAlpha, png::BitDepth::Eight) => buffer
+                .chunks_exact(2)
+                .flat_map(|va| [va[0], va[0], va[0], va[1]])
+                .collect(),
+            (color_type, bit_depth) => {
+                return Err(RendererErrors::UnsupportedImageFormat(format!(
+                    "{} is {:?} {:?}",
+                    path.display(),
+                    color_type,
+                    bit_depth
+                ))
+                .into())
+            }
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+/// Compares `frame` with the golden image at `golden_path`, channels may differ by up to
+/// `tolerance`. A missing golden image is an error, new golden images are recorded by
+/// running with UPDATE_GOLDEN_ENV set. On a mismatch the frame is saved next to it as
+/// `<name>.actual.png`.
+pub fn compare_with_golden(frame: &Frame, golden_path: &Path, tolerance: u8) -> Result<()> {
+    let update = env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|value| value != "0");
+    compare_or_update_golden(frame, golden_path, tolerance, update)
+}
+
+fn compare_or_update_golden(
+    frame: &Frame,
+    golden_path: &Path,
+    tolerance: u8,
+    update: bool,
+) -> Result<()> {
+    if update {
+        return frame.save_png(golden_path);
+    }
+    if !golden_path.exists() {
+        return Err(RendererErrors::GoldenImageDoesNotExist {
+            golden: golden_path.display().to_string(),
+            env: UPDATE_GOLDEN_ENV,
+        }
+        .into());
+    }
+
+    let golden = Frame::load_png(golden_path)?;
+    let diff = frame.compare(&golden)?;
+    if diff.max_difference <= tolerance {
+        return Ok(());
+    }
+
+    let actual_path = get_actual_path(golden_path);
+    frame.save_png(&actual_path)?;
+    Err(RendererErrors::GoldenImageMismatch {
+        golden: golden_path.display().to_string(),
+        actual: actual_path.display().to_string(),
+        differing_pixels: diff.differing_pixels,
+        max_difference: diff.max_difference,
+    }
+    .into())
+}
+
+fn get_actual_path(golden_path: &Path) -> PathBuf {
+    let stem = golden_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    golden_path.with_file_name(format!("{}.actual.png", stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_frame(color: [u8; 4]) -> Frame {
+        let mut frame = Frame::new(4, 3);
+        for pixel in frame.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+        frame
+    }
+
+    fn get_golden_dir(test: &str) -> PathBuf {
+        env::temp_dir().join(format!("golden_{}_{}", test, std::process::id()))
+    }
+
+    #[test]
+    fn missing_golden_images_fail_unless_updating() -> Result<()> {
+        let dir = get_golden_dir("missing");
+        let golden_path = dir.join("frame.png");
+        let frame = create_frame([10, 20, 30, 255]);
+
+        let err = compare_or_update_golden(&frame, &golden_path, 0, false).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RendererErrors>(),
+            Some(RendererErrors::GoldenImageDoesNotExist { .. })
+        ));
+        assert!(!golden_path.exists());
+
+        compare_or_update_golden(&frame, &golden_path, 0, true)?;
+        assert_eq!(Frame::load_png(&golden_path)?, frame);
+        compare_or_update_golden(&frame, &golden_path, 0, false)?;
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn mismatching_frames_are_saved_next_to_the_golden_image() -> Result<()> {
+        let dir = get_golden_dir("mismatch");
+        let golden_path = dir.join("frame.png");
+        create_frame([10, 20, 30, 255]).save_png(&golden_path)?;
+
+        let frame = create_frame([10, 24, 30, 255]);
+        compare_or_update_golden(&frame, &golden_path, 4, false)?;
+        let err = compare_or_update_golden(&frame, &golden_path, 3, false).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RendererErrors>(),
+            Some(RendererErrors::GoldenImageMismatch {
+                differing_pixels: 12,
+                max_difference: 4,
+                ..
+            })
+        ));
+        assert_eq!(Frame::load_png(&dir.join("frame.actual.png"))?, frame);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use super::{asset_store::AssetStore, Renderer, TextureFlip};
+use anyhow::{Error, Result};
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas, RenderTarget, TextureCreator},
+    surface::Surface,
+    video::Window,
+};
+
+/// `Canvas::texture_creator` only exists for window and surface canvases
+pub trait CanvasTarget: RenderTarget + Sized {
+    fn create_texture_creator(canvas: &Canvas<Self>) -> TextureCreator<Self::Context>;
+}
+
+impl CanvasTarget for Window {
+    fn create_texture_creator(canvas: &Canvas<Self>) -> TextureCreator<Self::Context> {
+        canvas.texture_creator()
+    }
+}
+
+impl<'s> CanvasTarget for Surface<'s> {
+    fn create_texture_creator(canvas: &Canvas<Self>) -> TextureCreator<Self::Context> {
+        canvas.texture_creator()
+    }
+}
+
+/// Draws with an SDL canvas, either a window canvas or a software canvas over a Surface
+pub struct SdlRenderer<T: CanvasTarget> {
+    /// NOTE: Declared first so it is dropped, destroying its textures, before the renderer
+    asset_store: AssetStore,
+    canvas: Canvas<T>,
+    texture_creator: TextureCreator<T::Context>,
+}
+
+impl<T: CanvasTarget> SdlRenderer<T> {
+    /// NOTE: The canvas is switched to alpha blending so translucent colors blend
+    pub fn new(mut canvas: Canvas<T>, asset_store: AssetStore) -> Self {
+        canvas.set_blend_mode(BlendMode::Blend);
+        let texture_creator = T::create_texture_creator(&canvas);
+        Self {
+            asset_store,
+            canvas,
+            texture_creator,
+        }
+    }
+
+    pub fn into_canvas(self) -> Canvas<T> {
+        self.canvas
+    }
+
+    pub fn get_canvas(&self) -> &Canvas<T> {
+        &self.canvas
+    }
+
+    pub fn get_canvas_mut(&mut self) -> &mut Canvas<T> {
+        &mut self.canvas
+    }
+
+    pub fn get_texture_creator(&self) -> &TextureCreator<T::Context> {
+        &self.texture_creator
+    }
+
+    pub fn get_asset_store(&self) -> &AssetStore {
+        &self.asset_store
+    }
+
+    pub fn get_asset_store_mut(&mut self) -> &mut AssetStore {
+        &mut self.asset_store
+    }
+
+    pub fn load_all_textures(&mut self) -> Result<()> {
+        self.asset_store.load_all_textures(&self.texture_creator)
+    }
+}
+
+impl<T: CanvasTarget> Renderer for SdlRenderer<T> {
+    fn clear(&mut self, color: Color) -> Result<()> {
+        self.canvas.set_draw_color(color);
+        self.canvas.clear();
+        Ok(())
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<()> {
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rect(rect).map_err(Error::msg)
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<()> {
+        self.canvas.set_draw_color(color);
+        self.canvas.draw_rect(rect).map_err(Error::msg)
+    }
+
+    fn draw_line(&mut self, start: (i32, i32), end: (i32, i32), color: Color) -> Result<()> {
+        self.canvas.set_draw_color(color);
+        self.canvas.draw_line(start, end).map_err(Error::msg)
+    }
+
+    fn draw_texture(
+        &mut self,
+        texture_id: &str,
+        src: Option<Rect>,
+        dst: Rect,
+        flip: TextureFlip,
+    ) -> Result<()> {
+        let texture = self
+            .asset_store
+            .load_texture(&self.texture_creator, texture_id)?;
+        self.canvas
+            .copy_ex(texture, src, dst, 0.0, None, flip.horizontal, flip.vertical)
+            .map_err(Error::msg)
+    }
+
+    /// NOTE: The text is rasterized into a new texture every call, fine for debug text
+    fn draw_text(&mut self, text: &str, position: (i32, i32), color: Color) -> Result<()> {
+        // SDL_ttf refuses to render an empty string
+        if text.is_empty() {
+            return Ok(());
+        }
+        let surface = self.asset_store.load_font()?.render(text).blended(color)?;
+        let texture = self.texture_creator.create_texture_from_surface(&surface)?;
+        let dst = Rect::new(position.0, position.1, surface.width(), surface.height());
+        self.canvas.copy(&texture, None, dst).map_err(Error::msg)?;
+        // SAFETY: The texture was created by this renderer's texture creator, which is alive
+        unsafe { texture.destroy() };
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<()> {
+        self.canvas.present();
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+//! Shapes rasterized into fill_rect spans and draw_line calls, so they look the same on
+//! every renderer. Coordinates are screen pixels, a pixel is covered when its center is
+//! inside the shape.
+
+use super::Renderer;
+use anyhow::Result;
+use sdl2::{pixels::Color, rect::Rect};
+
+pub fn fill_circle(
+    renderer: &mut dyn Renderer,
+    center: (f64, f64),
+    radius: f64,
+    color: Color,
+) -> Result<()> {
+    draw_ring(renderer, center, radius, 0., color)
+}
+
+/// Circle outline, the stroke grows inwards from the radius
+pub fn draw_circle(
+    renderer: &mut dyn Renderer,
+    center: (f64, f64),
+    radius: f64,
+    stroke_width: u32,
+    color: Color,
+) -> Result<()> {
+    let inner_radius = (radius - stroke_width as f64).max(0.);
+    draw_ring(renderer, center, radius, inner_radius, color)
+}
+
+fn draw_ring(
+    renderer: &mut dyn Renderer,
+    center: (f64, f64),
+    outer_radius: f64,
+    inner_radius: f64,
+    color: Color,
+) -> Result<()> {
+    if outer_radius <= 0. {
+        return Ok(());
+    }
+    let first_row = (center.1 - outer_radius).floor() as i32;
+    let last_row = (center.1 + outer_radius).ceil() as i32;
+    for y in first_row..=last_row {
+        let dy = y as f64 + 0.5 - center.1;
+        if dy.abs() > outer_radius {
+            continue;
+        }
+        let outer = (outer_radius * outer_radius - dy * dy).sqrt();
+        if dy.abs() >= inner_radius {
+            fill_span(renderer, y, center.0 - outer, center.0 + outer, color)?;
+            continue;
+        }
+        let inner = (inner_radius * inner_radius - dy * dy).sqrt();
+        fill_span(renderer, y, center.0 - outer, center.0 - inner, color)?;
+        fill_span(renderer, y, center.0 + inner, center.0 + outer, color)?;
+    }
+    Ok(())
+}
+
+/// Even-odd scanline fill, works for concave and self intersecting polygons too
+pub fn fill_polygon(
+    renderer: &mut dyn Renderer,
+    points: &[(f64, f64)],
+    color: Color,
+) -> Result<()> {
+    if points.len() < 3 {
+        return Ok(());
+    }
+    let min_y = points.iter().map(|point| point.1).fold(f64::MAX, f64::min);
+    let max_y = points.iter().map(|point| point.1).fold(f64::MIN, f64::max);
+
+    let mut crossings = vec![];
+    for y in min_y.floor() as i32..=max_y.ceil() as i32 {
+        let center_y = y as f64 + 0.5;
+        crossings.clear();
+        for (index, start) in points.iter().enumerate() {
+            let end = points[(index + 1) % points.len()];
+            if (start.1 <= center_y && center_y < end.1)
+                || (end.1 <= center_y && center_y < start.1)
+            {
+                let t = (center_y - start.1) / (end.1 - start.1);
+                crossings.push(start.0 + t * (end.0 - start.0));
+            }
+        }
+        crossings.sort_by(f64::total_cmp);
+        for pair in crossings.chunks_exact(2) {
+            fill_span(renderer, y, pair[0], pair[1], color)?;
+        }
+    }
+    Ok(())
+}
+
+/// Closed outline through every point
+pub fn draw_polygon(
+    renderer: &mut dyn Renderer,
+    points: &[(f64, f64)],
+    stroke_width: u32,
+    color: Color,
+) -> Result<()> {
+    for (index, start) in points.iter().enumerate() {
+        let end = points[(index + 1) % points.len()];
+        draw_line(renderer, *start, end, stroke_width, color)?;
+    }
+    Ok(())
+}
+
+/// Lines wider than a pixel are filled as a rectangle centered on the line
+pub fn draw_line(
+    renderer: &mut dyn Renderer,
+    start: (f64, f64),
+    end: (f64, f64),
+    stroke_width: u32,
+    color: Color,
+) -> Result<()> {
+    if stroke_width <= 1 {
+        return renderer.draw_line(round_point(start), round_point(end), color);
+    }
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0. {
+        return Ok(());
+    }
+    let half_width = stroke_width as f64 / 2.;
+    let normal = (-dy / length * half_width, dx / length * half_width);
+    fill_polygon(
+        renderer,
+        &[
+            (start.0 + normal.0, start.1 + normal.1),
+            (end.0 + normal.0, end.1 + normal.1),
+            (end.0 - normal.0, end.1 - normal.1),
+            (start.0 - normal.0, start.1 - normal.1),
+        ],
+        color,
+    )
+}
+
+/// Rectangle outline, the stroke grows inwards
+pub fn draw_rect(
+    renderer: &mut dyn Renderer,
+    rect: Rect,
+    stroke_width: u32,
+    color: Color,
+) -> Result<()> {
+    if stroke_width <= 1 {
+        return renderer.draw_rect(rect, color);
+    }
+    if stroke_width * 2 >= rect.width() || stroke_width * 2 >= rect.height() {
+        return renderer.fill_rect(rect, color);
+    }
+    let inner_height = rect.height() - 2 * stroke_width;
+    let stroke = stroke_width as i32;
+    renderer.fill_rect(
+        Rect::new(rect.x(), rect.y(), rect.width(), stroke_width),
+        color,
+    )?;
+    renderer.fill_rect(
+        Rect::new(rect.x(), rect.bottom() - stroke, rect.width(), stroke_width),
+        color,
+    )?;
+    renderer.fill_rect(
+        Rect::new(rect.x(), rect.y() + stroke, stroke_width, inner_height),
+        color,
+    )?;
+    renderer.fill_rect(
+        Rect::new(
+            rect.right() - stroke,
+            rect.y() + stroke,
+            stroke_width,
+            inner_height,
+        ),
+        color,
+    )
+}
+
+/// Covers the pixels in row `y` whose centers are between `start` and `end`
+fn fill_span(
+    renderer: &mut dyn Renderer,
+    y: i32,
+    start: f64,
+    end: f64,
+    color: Color,
+) -> Result<()> {
+    let first = (start - 0.5).ceil() as i32;
+    let last = (end - 0.5).floor() as i32;
+    if last < first {
+        return Ok(());
+    }
+    renderer.fill_rect(Rect::new(first, y, (last - first + 1) as u32, 1), color)
+}
+
+fn round_point(point: (f64, f64)) -> (i32, i32) {
+    (point.0.round() as i32, point.1.round() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::recording_renderer::{DrawCall, RecordingRenderer};
+
+    const COLOR: Color = Color::RGB(255, 0, 0);
+
+    fn get_rects(renderer: &mut RecordingRenderer) -> Vec<Rect> {
+        renderer
+            .take_draw_calls()
+            .into_iter()
+            .map(|call| match call {
+                DrawCall::FillRect { rect, color } if color == COLOR => rect,
+                call => panic!("unexpected draw call {:?}", call),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filling_a_small_circle() -> Result<()> {
+        let mut renderer = RecordingRenderer::new();
+        fill_circle(&mut renderer, (2., 2.), 2., COLOR)?;
+        assert_eq!(
+            get_rects(&mut renderer),
+            [
+                Rect::new(1, 0, 2, 1),
+                Rect::new(0, 1, 4, 1),
+                Rect::new(0, 2, 4, 1),
+                Rect::new(1, 3, 2, 1),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filling_a_concave_polygon() -> Result<()> {
+        let mut renderer = RecordingRenderer::new();
+        // A 6x4 block with a 2x2 notch cut into the middle of its bottom edge
+        let points = [
+            (0., 0.),
+            (6., 0.),
+            (6., 4.),
+            (4., 4.),
+            (4., 2.),
+            (2., 2.),
+            (2., 4.),
+            (0., 4.),
+        ];
+        fill_polygon(&mut renderer, &points, COLOR)?;
+        assert_eq!(
+            get_rects(&mut renderer),
+            [
+                Rect::new(0, 0, 6, 1),
+                Rect::new(0, 1, 6, 1),
+                Rect::new(0, 2, 2, 1),
+                Rect::new(4, 2, 2, 1),
+                Rect::new(0, 3, 2, 1),
+                Rect::new(4, 3, 2, 1),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rect_strokes_grow_inwards() -> Result<()> {
+        let mut renderer = RecordingRenderer::new();
+        draw_rect(&mut renderer, Rect::new(0, 0, 10, 8), 2, COLOR)?;
+        assert_eq!(
+            get_rects(&mut renderer),
+            [
+                Rect::new(0, 0, 10, 2),
+                Rect::new(0, 6, 10, 2),
+                Rect::new(0, 2, 2, 4),
+                Rect::new(8, 2, 2, 4),
+            ]
+        );
+
+        // Strokes wider than half the rect fill all of it
+        draw_rect(&mut renderer, Rect::new(1, 2, 6, 4), 3, COLOR)?;
+        assert_eq!(get_rects(&mut renderer), [Rect::new(1, 2, 6, 4)]);
+        Ok(())
+    }
+}
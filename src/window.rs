@@ -0,0 +1,111 @@
+/// Resource with the size of the window and how the virtual resolution is scaled into it.
+/// The game always draws in the virtual resolution, it is scaled up or down to fit the
+/// window keeping its aspect ratio and the rest of the window is letterboxed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowInfo {
+    /// Window size in pixels
+    pub width: u32,
+    pub height: u32,
+    pub virtual_width: u32,
+    pub virtual_height: u32,
+    pub is_fullscreen: bool,
+}
+
+impl WindowInfo {
+    pub fn new(size: (u32, u32), virtual_size: (u32, u32)) -> Self {
+        Self {
+            width: size.0,
+            height: size.1,
+            virtual_width: virtual_size.0,
+            virtual_height: virtual_size.1,
+            is_fullscreen: false,
+        }
+    }
+
+    /// Window pixels per virtual pixel
+    pub fn get_scale(&self) -> f64 {
+        if self.virtual_width == 0 || self.virtual_height == 0 {
+            return 1.;
+        }
+        let scale_x = self.width as f64 / self.virtual_width as f64;
+        let scale_y = self.height as f64 / self.virtual_height as f64;
+        scale_x.min(scale_y)
+    }
+
+    /// Part of the window (x, y, width, height) the virtual screen is drawn into, it is
+    /// centered so the bars on both sides have the same size
+    pub fn get_viewport(&self) -> (i32, i32, u32, u32) {
+        let scale = self.get_scale();
+        let width = ((self.virtual_width as f64 * scale).round() as u32).min(self.width);
+        let height = ((self.virtual_height as f64 * scale).round() as u32).min(self.height);
+        (
+            ((self.width - width) / 2) as i32,
+            ((self.height - height) / 2) as i32,
+            width,
+            height,
+        )
+    }
+
+    /// Converts a window position to the virtual resolution, positions on the bars are
+    /// outside of the virtual screen. None while the window has no area (e.g. minimized).
+    /// NOTE: SDL already does this for the positions in mouse events
+    pub fn window_to_virtual(&self, position: (i32, i32)) -> Option<(f64, f64)> {
+        let scale = self.get_scale();
+        if scale <= 0. {
+            return None;
+        }
+        let (x, y, _, _) = self.get_viewport();
+        Some((
+            (position.0 - x) as f64 / scale,
+            (position.1 - y) as f64 / scale,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIRTUAL_SIZE: (u32, u32) = (320, 180);
+
+    #[test]
+    fn windows_with_the_virtual_aspect_ratio_are_filled() {
+        let window = WindowInfo::new((640, 360), VIRTUAL_SIZE);
+        assert_eq!(window.get_scale(), 2.);
+        assert_eq!(window.get_viewport(), (0, 0, 640, 360));
+        assert_eq!(window.window_to_virtual((100, 50)), Some((50., 25.)));
+    }
+
+    #[test]
+    fn wider_windows_have_bars_on_the_sides() {
+        let window = WindowInfo::new((1000, 360), VIRTUAL_SIZE);
+        assert_eq!(window.get_scale(), 2.);
+        assert_eq!(window.get_viewport(), (180, 0, 640, 360));
+        assert_eq!(window.window_to_virtual((180, 0)), Some((0., 0.)));
+        assert_eq!(window.window_to_virtual((820, 360)), Some((320., 180.)));
+        // On the left bar
+        assert_eq!(window.window_to_virtual((178, 10)), Some((-1., 5.)));
+    }
+
+    #[test]
+    fn taller_windows_have_bars_above_and_below() {
+        let window = WindowInfo::new((480, 600), VIRTUAL_SIZE);
+        assert_eq!(window.get_scale(), 1.5);
+        assert_eq!(window.get_viewport(), (0, 165, 480, 270));
+        assert_eq!(window.window_to_virtual((240, 300)), Some((160., 90.)));
+        // On the bottom bar
+        assert_eq!(window.window_to_virtual((0, 450)), Some((0., 190.)));
+    }
+
+    #[test]
+    fn windows_without_area_have_no_virtual_positions() {
+        let window = WindowInfo::new((0, 0), VIRTUAL_SIZE);
+        assert_eq!(window.get_scale(), 0.);
+        assert_eq!(window.get_viewport(), (0, 0, 0, 0));
+        assert_eq!(window.window_to_virtual((10, 10)), None);
+
+        let window = WindowInfo::new((640, 0), VIRTUAL_SIZE);
+        assert_eq!(window.get_viewport(), (320, 0, 0, 0));
+        assert_eq!(window.window_to_virtual((10, 10)), None);
+    }
+}